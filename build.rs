@@ -1,5 +1,4 @@
 use clap_complete::{generate_to, Shell};
-use os_release::OsRelease;
 use std::env;
 include!("src/cli.rs");
 
@@ -14,12 +13,6 @@ fn generate_completions() {
 }
 
 fn main() {
-    let is_aosc = OsRelease::new().unwrap().name.contains("AOSC OS");
-    if cfg!(feature = "aosc") && !is_aosc {
-        panic!("It appears that you are not using AOSC OS, please re-compile apt-gen-list with the --no-default-features option");
-    } else if !cfg!(feature = "aosc") && is_aosc {
-        println!("cargo:warning=It appears that you are running apt-gen-list on AOSC OS without distro-specific features enabled. Please re-compile apt-gen-list with the --features aosc option.")
-    }
     println!("cargo:rerun-if-env-changed=AGL_GEN_COMPLETIONS");
     if env::var("AGL_GEN_COMPLETIONS").is_ok() {
         generate_completions();
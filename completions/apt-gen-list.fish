@@ -1,6 +1,12 @@
-complete -c apt-gen-list -n "__fish_use_subcommand" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_use_subcommand" -s V -l version -d 'Prints version information'
+complete -c apt-gen-list -n "__fish_use_subcommand" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_use_subcommand" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_use_subcommand" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_use_subcommand" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_use_subcommand" -s V -l version -d 'Print version information'
+complete -c apt-gen-list -n "__fish_use_subcommand" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_use_subcommand" -l no-refresh -d 'Do not refresh the package lists after applying changes'
 complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "set-branch" -d 'Set APT repository branch (e.g., stable)'
+complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "set-format" -d 'Set format of the generated APT sources'
 complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "set-mirror" -d 'Set APT repository mirror'
 complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "add-mirror" -d 'Add additional APT repository mirror'
 complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "remove-mirror" -d 'Remove APT repository mirror'
@@ -10,38 +16,174 @@ complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "remove-component" -d
 complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "add-custom-mirror" -d 'Add custom repository mirror'
 complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "remove-custom-mirror" -d 'Remove custom repository mirror'
 complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "speedtest" -d 'Run speed-test on available mirrors'
+complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "import" -d 'Create the status from the APT sources currently in use'
+complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "regenerate" -d 'Update mirror URLs from the repository data and regenerate the APT sources'
+complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "check" -d 'Check that the APT sources and mirror URLs still match the configuration'
+complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "freshness" -d 'Check how far mirrors lag behind the origin mirror'
+complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "history" -d 'Show previously applied configurations'
+complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "undo" -d 'Restore the configuration in effect before the latest change'
+complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "revert" -d 'Restore a configuration from history'
 complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "list-mirrors" -d 'Show available mirror list'
 complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "set-fastest-mirror-as-default" -d 'Set fastest mirror as default'
-complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "reset-mirror" -d 'Reset mirror to default'
-complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "help" -d 'Prints this message or the help of the given subcommand(s)'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from set-branch" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from set-branch" -s V -l version -d 'Prints version information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from set-mirror" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from set-mirror" -s V -l version -d 'Prints version information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from add-mirror" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from add-mirror" -s V -l version -d 'Prints version information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-mirror" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-mirror" -s V -l version -d 'Prints version information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from status" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from status" -s V -l version -d 'Prints version information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from add-component" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from add-component" -s V -l version -d 'Prints version information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-component" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-component" -s V -l version -d 'Prints version information'
+complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "reset-mirror" -d 'Reset mirror to the default of the distro profile'
+complete -c apt-gen-list -n "__fish_use_subcommand" -f -a "help" -d 'Print this message or the help of the given subcommand(s)'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-branch" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-branch" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-branch" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-branch" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-branch" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-branch" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-format" -l signed-by -d 'Keyring the generated sources are signed by, an empty KEYRING stops referencing one' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-format" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-format" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-format" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-format" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-format" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-format" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-mirror" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-mirror" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-mirror" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-mirror" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-mirror" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-mirror" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-mirror" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-mirror" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-mirror" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-mirror" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-mirror" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-mirror" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-mirror" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-mirror" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-mirror" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-mirror" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-mirror" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-mirror" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from status" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from status" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from status" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from status" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from status" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from status" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-component" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-component" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-component" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-component" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-component" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-component" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-component" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-component" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-component" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-component" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-component" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-component" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-custom-mirror" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-custom-mirror" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-custom-mirror" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
 complete -c apt-gen-list -n "__fish_seen_subcommand_from add-custom-mirror" -s s -l also-set-mirror -d 'also set mirror as default'
 complete -c apt-gen-list -n "__fish_seen_subcommand_from add-custom-mirror" -s a -l also-add-mirror -d 'also add mirror to list'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from add-custom-mirror" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from add-custom-mirror" -s V -l version -d 'Prints version information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-custom-mirror" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-custom-mirror" -s V -l version -d 'Prints version information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-custom-mirror" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-custom-mirror" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from add-custom-mirror" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-custom-mirror" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-custom-mirror" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-custom-mirror" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-custom-mirror" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-custom-mirror" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from remove-custom-mirror" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -s r -l rounds -d 'Test each mirror N times and rank mirrors by their median speed' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -s j -l concurrency -d 'Test at most N mirrors at the same time, implies --parallel' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -s t -l top -d 'Only test the download speed of the N mirrors with the lowest latency' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -l connect-timeout -d 'Seconds allowed to connect to a mirror' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -l read-timeout -d 'Seconds a mirror may take to respond or to send more data' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -l deadline -d 'Stop testing after SECONDS and rank mirrors by what was measured so far' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
 complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -s p -l parallel -d 'Test mirror performance concurrently, test will take a shorter amount of time, but results will only serve as a rough estimate and could vary between runs'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -s V -l version -d 'Prints version information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from list-mirrors" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from list-mirrors" -s V -l version -d 'Prints version information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -s V -l version -d 'Prints version information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from reset-mirror" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from reset-mirror" -s V -l version -d 'Prints version information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from help" -s h -l help -d 'Prints help information'
-complete -c apt-gen-list -n "__fish_seen_subcommand_from help" -s V -l version -d 'Prints version information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -l enabled -d 'Only test the mirrors currently in use'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -l include-custom -d 'Also test custom mirrors'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from speedtest" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from import" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from import" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from import" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from import" -l apply -d 'Also regenerate the APT sources from the imported status'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from import" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from import" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from import" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from regenerate" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from regenerate" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from regenerate" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from regenerate" -l drop-missing -d 'Disable mirrors which are no longer defined instead of keeping their stored URL'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from regenerate" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from regenerate" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from regenerate" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from check" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from check" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from check" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from check" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from check" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from check" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from freshness" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from freshness" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from freshness" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from freshness" -l enabled -d 'Only check the mirrors currently in use'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from freshness" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from freshness" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from freshness" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from history" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from history" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from history" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from history" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from history" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from history" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from undo" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from undo" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from undo" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from undo" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from undo" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from undo" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from revert" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from revert" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from revert" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from revert" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from revert" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from revert" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from list-mirrors" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from list-mirrors" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from list-mirrors" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from list-mirrors" -s s -l speed -d 'Show the speed measured by the latest speed test and sort by it'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from list-mirrors" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from list-mirrors" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from list-mirrors" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -s r -l rounds -d 'Test each mirror N times and pick the one with the best median speed' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -s n -l count -d 'Enable the N fastest mirrors, fastest first' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -l margin -d 'Keep the current mirror first unless the fastest one is more than PERCENT faster' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -l max-lag -d 'Skip mirrors lagging behind the origin mirror by more than AGE (e.g. 12h, 2d)' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -l max-age -d 'Reuse speed test results younger than AGE (e.g. 30m, 12h, 1d) instead of testing again' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -s j -l concurrency -d 'Test at most N mirrors at the same time, implies --parallel' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -s t -l top -d 'Only test the download speed of the N mirrors with the lowest latency' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -l connect-timeout -d 'Seconds allowed to connect to a mirror' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -l read-timeout -d 'Seconds a mirror may take to respond or to send more data' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -l deadline -d 'Stop testing after SECONDS and rank mirrors by what was measured so far' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -s p -l parallel -d 'Test mirror performance concurrently, test will take a shorter amount of time, but results will only serve as a rough estimate and could vary between runs'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -l enabled -d 'Only test the mirrors currently in use'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -l include-custom -d 'Also test custom mirrors'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from set-fastest-mirror-as-default" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from reset-mirror" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from reset-mirror" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from reset-mirror" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from reset-mirror" -s h -l help -d 'Print help information'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from reset-mirror" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from reset-mirror" -l no-refresh -d 'Do not refresh the package lists after applying changes'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from help" -l root -d 'Operate on the system installed in ROOT instead of the host' -r
+complete -c apt-gen-list -n "__fish_seen_subcommand_from help" -l profile -d 'Distro profile of the target system, detected from os-release by default' -r -f -a "{aosc	,aosc-retro	,debian	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from help" -s o -l output -d 'Output format of status, list-mirrors and speedtest' -r -f -a "{text	,json	,yaml	}"
+complete -c apt-gen-list -n "__fish_seen_subcommand_from help" -l dry-run -d 'Show a diff of the files that would be changed without writing anything'
+complete -c apt-gen-list -n "__fish_seen_subcommand_from help" -l no-refresh -d 'Do not refresh the package lists after applying changes'
@@ -2,14 +2,29 @@
 branch = Branch: {$branch}
 component = Component: {$comp}
 mirror = Mirror: {$mirror}
+format = Format: {$format}
 custom = [Custom]
+history-initial = (initial)
 
 # messages
 set-branch = Setting {$branch} as branch
+set-format = Setting {$format} as sources format
+undo = Restoring the configuration in effect before the latest change ...
+revert = Restoring configuration #{$id} ...
+import = Importing configuration from the APT sources in use ...
+import-unmapped = {$path}:{$line}: {$reason}, skipping
+import-default = {$error} Using the default configuration of the distro profile.
 mirror-list-explain = A '*' or a highlight in front indicates that this mirror is in use:
-test-mirrors = Testing mirrors ...
-test-mirrors-sync = Testing mirrors ({$count}/{$all}) ...
+test-mirrors-latency = Measuring the latency of mirrors ...
+test-mirror-round = round {$round}/{$rounds}
 set-fastest-mirror = Fastest mirror: {$mirror}, speed: {$speed}, Setting {$mirror} as default mirror ...
+fastest-mirror-confidence = Confidence: {$confidence} (median speed {$percent}% above the runner-up {$mirror})
+set-fastest-mirrors = Fastest mirrors: {$mirrors}, setting them as mirrors in this order ...
+keep-mirror = Keeping {$mirror}, {$fastest} is only {$percent}% faster (margin: {$margin}%).
+keep-fastest-mirror = {$mirror} is already the fastest mirror, keeping it.
+confidence-high = high
+confidence-medium = medium
+confidence-low = low, more --rounds may help
 enable-comp = Enabling component {$comp} ...
 disable-comp = Disabling component {$comp} ...
 set-mirror = Setting {$mirror} as mirror!
@@ -18,11 +33,17 @@ add-custom-mirror = Adding custom mirror {$mirror} to {$path}
 remove-mirror = Removing {$mirror} from sources.list ...
 remove-custom-mirror = Removing custom mirror {$mirror} from {$path}
 write-status = Writing apt-gen-list status file ...
-write-sources = Writing /etc/apt/sources.list ...
+write-sources = Writing {$path} ...
+remove-sources = Removing {$path} ...
 write-omakase-config = Writing /etc/omakase/config.toml ...
-run-apt = Running `apt-get update` ...
+run-command = Running `{$command}` ...
+rollback = Failed to apply changes, restoring previous files ...
+recovered-transaction = Restored files left behind by an interrupted run of apt-gen-list.
+waiting-for-lock = Waiting for another run of apt-gen-list to finish ...
 run-oma = Running `oma refresh` ...
-run-atm-refresh = Running `atm refresh` ...
+skip-command = Skipping `{$command}`, it is not available in the target root ...
+duplicate-entry = Warning: {$path}:{$line} duplicates mirror {$mirror} managed by apt-gen-list, APT will see it twice.
+run-hook = Running hook {$hook} ...
 trying-get-mirror = Trying get mirror ...
 
 # error messages
@@ -31,10 +52,27 @@ comp-not-found = Component {$comp} does not exist.
 comp-already-enabled = Component {$comp} is already enabled.
 branch-not-found = Branch undefined or does not exist!
 branch-data-error = Cannot read branch list data!
+format-not-found = Sources format {$format} does not exist!
 mirror-not-found = Cannot find mirror: {$mirror}. Please use `apt-gen-list list-mirrors` to display a list of available mirrors, or use `apt-gen-list add-custom-mirror` to add a custom mirror.
 mirror-already-enabled = Mirror {$mirror} is already enabled!
 mirror-error = Failed to download test data from {$mirror}, please check your network connection!
 mirror-test-failed = Get All mirror failed! Please check your network connection!
+mirrors-lagging = No mirror which passed the speed test is recent enough for --max-lag!
+freshness-up-to-date = up to date
+freshness-expired = expired
+check-freshness = Checking how far mirrors lag behind ...
+exclude-lagging-mirror = Skipping {$mirror}, it lags behind by {$lag}.
+exclude-expired-mirror = Skipping {$mirror}, its InRelease has expired.
+exclude-unchecked-mirror = Skipping {$mirror}, its InRelease cannot be checked.
+speedtest-ok = OK
+speedtest-failed = Failed: {$reason}
+speedtest-timeout = timed out
+speedtest-http-status = HTTP status {$status}
+speedtest-tls = TLS error: {$error}
+speedtest-checksum-mismatch = test file checksum mismatch
+speedtest-skipped = skipped, other mirrors have a lower latency
+speedtest-deadline-exceeded = deadline passed before the test finished
+speedtest-cached = Using speed test results younger than {$age}.
 custom-mirror-not-found = Custom mirror {$mirror} does not exist!
 custom-mirror-already-exist = Custom mirror {$mirror} already exists!
 custom-mirror-not-url = mirror_url is not a URL!
@@ -42,9 +80,40 @@ custom-mirror-name-error = mirror_name does exist in distro mirror file!
 no-delete-only-mirror = You only have one mirror left, refusing to remove!
 no-delete-only-comp = Refusing to remove essential component "main".
 status-file-not-found = Status file ({$path}) does not exist! please use root user to run apt-gen-list to create status file!
-status-file-read-error = Status file is corrupt or too old, please run it with the root user to use the correct format
+status-file-read-error = Status file {$path} is corrupt, run `apt-gen-list import` to recreate it from the APT sources in use!
+status-file-too-new = Status file {$path} has version {$version}, which is newer than this apt-gen-list supports!
+status-migration-failed = Failed to migrate status file {$path} from version {$version}: {$error}
+unterminated-block = {$path} has a "# BEGIN apt-gen-list" line without a matching "# END apt-gen-list" line, add it after the entries apt-gen-list generated and try again.
 debs-path-in-url = A common mistake is to add '/debs' at the end of the repository URL (which is detected in your custom configuration), please try and remove this segment and try again.
 download-mirror-metadata-failed = Failed to download repository metadata from your custom mirror - it seems that your repository configuration is incorrect.
+os-release-not-found = Cannot read os-release from {$path}!
+command-failed = `{$command}` failed: {$status}
+hook-failed = Hook {$hook} failed: {$status}
+invalid-path = Invalid path: {$path}
+read-file-failed = Cannot read {$path}: {$error}
+parse-file-failed = Cannot parse {$path}: {$error}
+invalid-url = Invalid mirror URL: {$error}
+network-error = Network error: {$error}
+history-entry-not-found = History entry #{$id} does not exist! Please use `apt-gen-list history` to display the list of history entries.
+history-nothing-to-undo = There is no earlier configuration to restore!
+import-nothing = No APT sources entry matches the repository data, nothing to import!
+import-malformed = cannot parse entry
+import-source-packages = deb-src entries are not supported
+import-unknown-mirror = unknown mirror {$uri}
+import-unknown-suite = suite {$suite} is not part of any matching branch
+import-unknown-component = unknown component {$comp}
+check-sources = {$path} differs from the generated sources:
+check-mirror = Mirror {$mirror} ({$url}): {$reason}
+check-mirror-removed = no longer defined in the repository data or custom mirrors
+check-mirror-url-changed = URL is now {$url}
+check-clean = APT sources match the apt-gen-list configuration.
+check-drift = APT sources or mirror URLs differ from the apt-gen-list configuration!
+regenerate = Regenerating APT sources from the updated repository data ...
+regenerate-up-to-date = APT sources are up to date.
+regenerate-no-status = apt-gen-list is not configured yet, nothing to regenerate.
+no-default-status = Distro profile {$profile} has no default configuration!
+no-speedtest-probe = No file to test mirrors with is defined, please list one in /etc/apt-gen-list/speedtest.yml!
 
 # file content
 generated = # Generated by apt-gen-list. DO NOT EDIT THIS FILE!
+generated-block = # Generated by apt-gen-list. Lines up to the END marker are overwritten, add your own entries outside of them.
@@ -2,14 +2,29 @@
 branch = 分支：{$branch}
 component = 组件：{$comp}
 mirror = 镜像源：{$mirror}
+format = 源格式：{$format}
 custom = [自定义]
+history-initial = （初始配置）
 
 # messages
 set-branch = 已将 {$branch} 设置为默认分支
+set-format = 已将 {$format} 设置为源格式
+undo = 正在恢复最近一次更改前的配置 ...
+revert = 正在恢复第 {$id} 号配置 ...
+import = 正在从当前使用的 APT 源导入配置 ...
+import-unmapped = {$path}:{$line}：{$reason}，已跳过
+import-default = {$error} 将使用发行版配置的默认设置。
 mirror-list-explain = 行头的 '*' 或高亮代表正在使用该镜像源：
-test-mirrors = 正在测试镜像源性能 ...
-test-mirrors-sync = 正在测试镜像源性能 ({$count}/{$all}) ...
+test-mirrors-latency = 正在测量镜像源延迟 ...
+test-mirror-round = 第 {$round}/{$rounds} 轮
 set-fastest-mirror = 最快的镜像源为：{$mirror}，速率：{$speed}，现将 {$mirror} 设置为默认镜像源 ...
+fastest-mirror-confidence = 可信度：{$confidence}（中位速率比次快的 {$mirror} 高 {$percent}%）
+set-fastest-mirrors = 最快的镜像源：{$mirrors}，正在按此顺序设置为镜像源 ...
+keep-mirror = 保留 {$mirror}，{$fastest} 仅快 {$percent}%（阈值：{$margin}%）。
+keep-fastest-mirror = {$mirror} 已是最快的镜像源，将保留该镜像源。
+confidence-high = 高
+confidence-medium = 中
+confidence-low = 低，增加 --rounds 或许有帮助
 enable-comp = 正在启用 {$comp} 组件 ...
 disable-comp = 正在禁用 {$comp} 组件 ...
 set-mirror = 正在将 {$mirror} 设定为镜像源！
@@ -18,11 +33,17 @@ add-custom-mirror = 正在将 {$mirror} 的自定义镜像源信息写入 {$path
 remove-mirror = 正在从 sources.list 移除 {$mirror} 的镜像源信息 ...
 remove-custom-mirror = 正在从 {$path} 移除 {$mirror} 的自定义镜像源信息
 write-status = 正在写入 apt-gen-list 状态文件 ...
-write-sources = 正在生成 /etc/apt/sources.list ...
+write-sources = 正在生成 {$path} ...
+remove-sources = 正在移除 {$path} ...
 write-omakase-config = 正在生成 /etc/omakase/config.toml ...
-run-apt = 正在运行 `apt-get update` ...
+run-command = 正在运行 `{$command}` ...
+rollback = 应用更改失败，正在恢复原有文件 ...
+recovered-transaction = 已恢复上次中断运行的 apt-gen-list 所遗留的文件。
+waiting-for-lock = 正在等待另一个 apt-gen-list 进程结束……
 run-oma = 正在运行 `oma refresh` ...
-run-atm-refresh = 正在运行 `atm refresh` ...
+skip-command = 目标根目录中没有 `{$command}`，已跳过 ...
+duplicate-entry = 警告：{$path}:{$line} 与 apt-gen-list 管理的镜像源 {$mirror} 重复，APT 会重复读取该条目。
+run-hook = 正在运行钩子 {$hook} ...
 trying-get-mirror = 正在尝试访问源 ...
 
 
@@ -32,10 +53,27 @@ comp-not-found = 组件 {$comp} 不存在。
 comp-already-enabled = 组件 {$comp} 已启用。
 branch-not-found = 分支未定义或不存在！
 branch-data-error = 无法读取分支列表数据！
+format-not-found = 源格式 {$format} 不存在！
 mirror-not-found = 找不到镜像源：{$mirror} 。请使用 `apt-gen-list list-mirrors` 查看源列表，或使用 `apt-gen-list add-custom-mirror` 添加自定义源。
 mirror-already-enabled = 之前已启用 {$mirror} ！
 mirror-error = 无法从 {$mirror} 下载测试数据，请检查你的网络连接！
 mirror-test-failed = 无法测试任何镜像源！请检查你的网络连接！
+mirrors-lagging = 没有足够新的镜像源通过测速（见 --max-lag）！
+freshness-up-to-date = 已同步
+freshness-expired = 已过期
+check-freshness = 正在检查镜像源同步延迟 ...
+exclude-lagging-mirror = 跳过 {$mirror}，该镜像源落后 {$lag}。
+exclude-expired-mirror = 跳过 {$mirror}，其 InRelease 已过期。
+exclude-unchecked-mirror = 跳过 {$mirror}，无法检查其 InRelease。
+speedtest-ok = 成功
+speedtest-failed = 失败：{$reason}
+speedtest-timeout = 超时
+speedtest-http-status = HTTP 状态码 {$status}
+speedtest-tls = TLS 错误：{$error}
+speedtest-checksum-mismatch = 测试文件校验和不匹配
+speedtest-skipped = 已跳过，其他镜像源延迟更低
+speedtest-deadline-exceeded = 测试完成前已超过截止时间
+speedtest-cached = 使用 {$age} 内的测速结果。
 custom-mirror-not-found = 自定义镜像源 {$mirror} 不存在！
 custom-mirror-already-exist = 自定义镜像源 {$mirror} 已存在！
 custom-mirror-not-url = mirror_url 不是合法 URL ！
@@ -43,9 +81,40 @@ custom-mirror-name-error = mirror_name 未在镜像源数据文件中定义！
 no-delete-only-mirror = 无法移除唯一启用的镜像源！
 no-delete-only-comp = 程序已拒绝删除必需组件 "main" 。
 status-file-not-found = 状态文件 ({$path}) 不存在！请用 root 用户运行 apt-gen-list 以创建状态文件！
-status-file-read-error = 状态文件格式过老或已损坏，请用 root 用户运行该命令以修正状态文件！
+status-file-read-error = 状态文件 {$path} 已损坏，请运行 `apt-gen-list import` 从当前使用的 APT 软件源重新生成！
+status-file-too-new = 状态文件 {$path} 的版本 {$version} 高于当前 apt-gen-list 支持的版本！
+status-migration-failed = 无法将状态文件 {$path} 从版本 {$version} 迁移：{$error}
+unterminated-block = {$path} 中有 "# BEGIN apt-gen-list" 行，但没有对应的 "# END apt-gen-list" 行，请在 apt-gen-list 生成的条目之后添加该行后重试。
 debs-path-in-url = apt-gen-list 发现您的自定义 URL 结尾发现 '/debs' 字段，这是配置自定义软件源时的一大常见错误。请删去此节后重试。
 download-mirror-metadata-failed = 从自定义软件源元数据下载失败：您的软件源配置信息可能不正确。
+os-release-not-found = 无法从 {$path} 读取 os-release！
+command-failed = `{$command}` 运行失败：{$status}
+hook-failed = 钩子 {$hook} 运行失败：{$status}
+invalid-path = 无效路径：{$path}
+read-file-failed = 无法读取 {$path}：{$error}
+parse-file-failed = 无法解析 {$path}：{$error}
+invalid-url = 无效的镜像源 URL：{$error}
+network-error = 网络错误：{$error}
+history-entry-not-found = 历史记录 #{$id} 不存在！请使用 `apt-gen-list history` 查看历史记录列表。
+history-nothing-to-undo = 没有可恢复的更早配置！
+import-nothing = 没有与仓库数据相符的 APT 源条目，无可导入！
+import-malformed = 无法解析条目
+import-source-packages = 不支持 deb-src 条目
+import-unknown-mirror = 未知镜像源 {$uri}
+import-unknown-suite = 套件 {$suite} 不属于任何匹配的分支
+import-unknown-component = 未知组件 {$comp}
+check-sources = {$path} 与生成的软件源配置不一致：
+check-mirror = 镜像源 {$mirror}（{$url}）：{$reason}
+check-mirror-removed = 已不在仓库数据或自定义镜像源中
+check-mirror-url-changed = URL 已变更为 {$url}
+check-clean = APT 软件源与 apt-gen-list 配置一致。
+check-drift = APT 软件源或镜像源 URL 与 apt-gen-list 配置不一致！
+regenerate = 正在根据更新的仓库数据重新生成 APT 软件源……
+regenerate-up-to-date = APT 软件源已是最新。
+regenerate-no-status = apt-gen-list 尚未配置，无需重新生成。
+no-default-status = 发行版配置 {$profile} 没有默认配置！
+no-speedtest-probe = 未定义用于测试镜像源的文件，请在 /etc/apt-gen-list/speedtest.yml 中指定！
 
 # file content
 generated = # 本文件使用 apt-gen-list 生成，请勿编辑！
+generated-block = # 以下内容由 apt-gen-list 生成，END 标记之前的内容会被覆盖，请在标记之外添加自定义条目。
@@ -0,0 +1,238 @@
+use serde::Serialize;
+use similar::TextDiff;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    find_duplicate_entries, gen_sources_file_string,
+    history::{record_history, HistoryEntry},
+    hooks::{run_hooks, HookStage},
+    status::{backup_status_file, status_file_string},
+    transaction::{atomic_write, Transaction},
+    Context, DuplicateEntry, Error, Result, SourceFormat, Status,
+};
+
+/// Progress of [`apply_status`], reported before each step is taken
+pub enum ApplyStep<'a> {
+    WriteStatus,
+    WriteSources(&'a Path),
+    RemoveSources(&'a Path),
+    /// A command is about to run on the target system
+    RunCommand(&'a str),
+    /// A command is not available on the target system and is skipped
+    SkipCommand(&'a str),
+    /// A hook at this path of the target system is about to run
+    RunHook(&'a Path),
+    /// An entry apt-gen-list does not manage duplicates an enabled mirror
+    DuplicateEntry(&'a DuplicateEntry),
+    /// A step failed and all files are being restored
+    Rollback,
+}
+
+/// A change to a file, `None` stands for a file which does not exist
+#[derive(Serialize)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub old_content: Option<String>,
+    pub new_content: Option<String>,
+}
+
+impl FileChange {
+    pub(crate) fn new(path: &Path, new_content: Option<&str>) -> Self {
+        FileChange {
+            path: path.to_path_buf(),
+            old_content: fs::read_to_string(path).ok(),
+            new_content: new_content.map(str::to_string),
+        }
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.old_content != self.new_content
+    }
+
+    /// Render the change as a unified diff, empty if nothing changes
+    pub fn unified_diff(&self) -> String {
+        if !self.is_changed() {
+            return String::new();
+        }
+        let path = self.path.display().to_string();
+        let old_header = if self.old_content.is_some() {
+            &path
+        } else {
+            "/dev/null"
+        };
+        let new_header = if self.new_content.is_some() {
+            &path
+        } else {
+            "/dev/null"
+        };
+        TextDiff::from_lines(
+            self.old_content.as_deref().unwrap_or_default(),
+            self.new_content.as_deref().unwrap_or_default(),
+        )
+        .unified_diff()
+        .missing_newline_hint(false)
+        .header(old_header, new_header)
+        .to_string()
+    }
+}
+
+/// Write `status` and the APT sources generated from it to the target system,
+/// then refresh the package lists
+///
+/// `command` is recorded in the history along with `status`. Hooks of
+/// [`HookStage::PreApply`] run first and may abort, hooks of
+/// [`HookStage::PostApply`] run once everything is applied. All files are
+/// restored if any step in between fails. In dry-run mode nothing is written or run.
+/// Returns the changes made to each file, or that would be made in dry-run mode.
+pub fn apply_status<F: FnMut(ApplyStep)>(
+    ctx: &Context,
+    status: &Status,
+    command: &str,
+    on_step: F,
+) -> Result<Vec<FileChange>> {
+    apply(ctx, status, command, None, on_step)
+}
+
+/// Apply the status of `entry` like [`apply_status`], recording it as an undo
+/// so that the next [`find_undo_entry`](crate::find_undo_entry) goes further back
+pub fn apply_undo<F: FnMut(ApplyStep)>(
+    ctx: &Context,
+    entry: &HistoryEntry,
+    command: &str,
+    on_step: F,
+) -> Result<Vec<FileChange>> {
+    apply(ctx, &entry.status, command, Some(entry.id), on_step)
+}
+
+fn apply<F: FnMut(ApplyStep)>(
+    ctx: &Context,
+    status: &Status,
+    command: &str,
+    restored: Option<u64>,
+    mut on_step: F,
+) -> Result<Vec<FileChange>> {
+    // Held through the commit, the changes are computed from what is on disk
+    if !ctx.dry_run() {
+        ctx.lock()?;
+    }
+    let status_str = status_file_string(status)?;
+    let status_path = ctx.status_file();
+    let mut changes = vec![FileChange::new(&status_path, Some(&status_str))];
+    // Sources of the other format are dropped, otherwise APT would see every entry twice
+    for format in [SourceFormat::OneLine, SourceFormat::Deb822] {
+        let content = gen_sources_file_string(ctx, status, format)?;
+        changes.push(FileChange::new(
+            &ctx.path(format.path()),
+            content.as_deref(),
+        ));
+    }
+    for duplicate in find_duplicate_entries(ctx, status)? {
+        on_step(ApplyStep::DuplicateEntry(&duplicate));
+    }
+    if ctx.dry_run() {
+        return Ok(changes);
+    }
+    // A run which held the lock before may have been interrupted
+    Transaction::recover(&ctx.transaction_journal())?;
+    let source_file = status.source_format.path();
+    run_hooks(
+        ctx,
+        HookStage::PreApply,
+        &status_str,
+        source_file,
+        &mut on_step,
+    )?;
+    let history_path = ctx.history_file();
+    let history_str = record_history(&history_path, &status_path, status, command, restored)?;
+    let mut paths = vec![history_path.as_path()];
+    paths.extend(changes.iter().map(|change| change.path.as_path()));
+    backup_status_file(&status_path)?;
+    let transaction = Transaction::begin(ctx.transaction_journal(), &paths)?;
+    let result = (|| -> Result<()> {
+        on_step(ApplyStep::WriteStatus);
+        transaction.write(&status_path, status_str.as_bytes())?;
+        transaction.write(&history_path, history_str.as_bytes())?;
+        for change in &changes[1..] {
+            match &change.new_content {
+                Some(content) => {
+                    on_step(ApplyStep::WriteSources(&change.path));
+                    transaction.write(&change.path, content.as_bytes())?;
+                }
+                None if change.old_content.is_some() => {
+                    on_step(ApplyStep::RemoveSources(&change.path));
+                    transaction.remove(&change.path)?;
+                }
+                None => (),
+            }
+        }
+        if ctx.refresh() {
+            for command in ctx.profile()?.refresh_commands() {
+                run_command(ctx, command[0], &command[1..], &mut on_step)?;
+            }
+        }
+
+        Ok(())
+    })();
+    match result {
+        Ok(()) => transaction.commit()?,
+        Err(e) => {
+            on_step(ApplyStep::Rollback);
+            transaction.rollback()?;
+            return Err(e);
+        }
+    }
+    run_hooks(
+        ctx,
+        HookStage::PostApply,
+        &status_str,
+        source_file,
+        &mut on_step,
+    )?;
+
+    Ok(changes)
+}
+
+/// Roll back an apply interrupted by a crash, returns whether there was one
+///
+/// Waits for [`Context::lock`] first, a run still holding it may be applying.
+pub fn recover_transaction(ctx: &Context) -> Result<bool> {
+    ctx.lock()?;
+    Transaction::recover(&ctx.transaction_journal())
+}
+
+/// Write `content` to `path`, or only compute the change in dry-run mode
+pub fn write_file(ctx: &Context, path: &Path, content: &str) -> Result<FileChange> {
+    let change = FileChange::new(path, Some(content));
+    if !ctx.dry_run() {
+        atomic_write(path, content.as_bytes())?;
+    }
+
+    Ok(change)
+}
+
+fn run_command<F: FnMut(ApplyStep)>(
+    ctx: &Context,
+    program: &str,
+    args: &[&str],
+    on_step: &mut F,
+) -> Result<()> {
+    let command_line = [&[program], args].concat().join(" ");
+    match ctx.command(program) {
+        Some(mut command) => {
+            on_step(ApplyStep::RunCommand(&command_line));
+            let exit_status = command.args(args).status()?;
+            if !exit_status.success() {
+                return Err(Error::CommandFailed {
+                    command: command_line,
+                    status: exit_status,
+                });
+            }
+        }
+        None => on_step(ApplyStep::SkipCommand(&command_line)),
+    }
+
+    Ok(())
+}
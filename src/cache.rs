@@ -0,0 +1,103 @@
+use chrono::Local;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fs, time::Duration};
+
+use crate::{
+    is_root, list_mirrors,
+    speedtest::{sort_score_table, speedtest_mirrors},
+    transaction::atomic_write,
+    Context, MirrorScore, Result, SpeedtestOptions, SpeedtestOutcome,
+};
+
+/// Result of the latest speed test which measured a mirror
+#[derive(Deserialize, Serialize)]
+pub struct CachedScore {
+    /// Seconds since the Unix epoch
+    pub time: i64,
+    #[serde(flatten)]
+    pub score: MirrorScore,
+}
+
+impl CachedScore {
+    fn is_fresh(&self, now: i64, max_age: Duration) -> bool {
+        now.saturating_sub(self.time) <= i64::try_from(max_age.as_secs()).unwrap_or(i64::MAX)
+    }
+}
+
+/// Latest speed test results by mirror name
+///
+/// A missing or unreadable cache is empty, it only saves time.
+pub fn read_speedtest_cache(ctx: &Context) -> IndexMap<String, CachedScore> {
+    fs::read(ctx.speedtest_cache_file())
+        .ok()
+        .and_then(|file| serde_json::from_slice(&file).ok())
+        .unwrap_or_default()
+}
+
+/// Merge a table returned by [`get_mirror_score_table`](crate::get_mirror_score_table)
+/// into the cache
+///
+/// Mirrors which were skipped or cut off by the deadline keep their previous
+/// result, URLs tested before being added as custom mirrors are left out.
+/// Nothing is written in dry-run mode or without root.
+pub fn record_speedtest(ctx: &Context, mirrors_score_table: &[MirrorScore]) -> Result<()> {
+    if ctx.dry_run() || !is_root() {
+        return Ok(());
+    }
+    let mirrors = mirror_names(ctx)?;
+    let mut cache = read_speedtest_cache(ctx);
+    let time = Local::now().timestamp();
+    for score in mirrors_score_table {
+        if mirrors.contains(&score.name)
+            && !matches!(
+                score.outcome,
+                SpeedtestOutcome::Skipped | SpeedtestOutcome::DeadlineExceeded
+            )
+        {
+            cache.insert(
+                score.name.clone(),
+                CachedScore {
+                    time,
+                    score: score.clone(),
+                },
+            );
+        }
+    }
+    let cache_str = format!("{}\n", serde_json::to_string(&cache)?);
+
+    atomic_write(&ctx.speedtest_cache_file(), cache_str.as_bytes())
+}
+
+/// Cached results no older than `max_age` standing in for a speed test with
+/// `options`, ranked like a fresh one
+///
+/// `None` unless every mirror the speed test would measure has such a result,
+/// picking from part of them could miss the fastest.
+pub fn cached_score_table(
+    ctx: &Context,
+    options: &SpeedtestOptions,
+    max_age: Duration,
+) -> Result<Option<Vec<MirrorScore>>> {
+    let mirrors = speedtest_mirrors(ctx, options)?;
+    let now = Local::now().timestamp();
+    let mut cache = read_speedtest_cache(ctx);
+    let mut mirrors_score_table = Vec::new();
+    for mirror_name in mirrors.keys() {
+        match cache.swap_remove(mirror_name) {
+            Some(cached) if cached.is_fresh(now, max_age) => mirrors_score_table.push(cached.score),
+            _ => return Ok(None),
+        }
+    }
+    sort_score_table(&mut mirrors_score_table);
+
+    Ok(Some(mirrors_score_table))
+}
+
+/// Names of the mirrors in the repository data and the custom mirror file
+fn mirror_names(ctx: &Context) -> Result<HashSet<String>> {
+    Ok(list_mirrors(ctx)?
+        .into_iter()
+        .map(|mirror| mirror.name)
+        .collect())
+}
@@ -0,0 +1,180 @@
+use serde::Serialize;
+use std::fmt;
+
+use crate::{
+    apply::FileChange, fl, gen_sources_file_string, Context, Result, SourceFormat, Status,
+};
+
+/// Differences between the status and the target system, as found by [`check_status`]
+#[derive(Serialize)]
+pub struct CheckResult {
+    /// Sources files which differ from what the status generates, from the
+    /// content on disk to the expected one
+    pub sources: Vec<FileChange>,
+    pub mirrors: Vec<MirrorDrift>,
+}
+
+impl CheckResult {
+    pub fn is_clean(&self) -> bool {
+        self.sources.is_empty() && self.mirrors.is_empty()
+    }
+}
+
+/// An enabled mirror whose URL in the status is out of date
+#[derive(Serialize)]
+pub struct MirrorDrift {
+    pub name: String,
+    /// URL stored in the status
+    pub url: String,
+    #[serde(flatten)]
+    pub reason: MirrorDriftReason,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum MirrorDriftReason {
+    /// Neither the repository data nor the custom mirror file defines the mirror
+    Removed,
+    UrlChanged {
+        expected_url: String,
+    },
+}
+
+impl fmt::Display for MirrorDriftReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            MirrorDriftReason::Removed => fl!("check-mirror-removed"),
+            MirrorDriftReason::UrlChanged { expected_url } => {
+                fl!("check-mirror-url-changed", url = expected_url.as_str())
+            }
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+/// Compare the sources files of the target system and the mirror URLs stored
+/// in `status` with what they should be
+///
+/// Nothing is written, see [`CheckResult::is_clean`].
+pub fn check_status(ctx: &Context, status: &Status) -> Result<CheckResult> {
+    let mut sources = Vec::new();
+    for format in [SourceFormat::OneLine, SourceFormat::Deb822] {
+        let content = gen_sources_file_string(ctx, status, format)?;
+        let change = FileChange::new(&ctx.path(format.path()), content.as_deref());
+        if change.is_changed() {
+            sources.push(change);
+        }
+    }
+    let mirrors = status.clone().refresh_mirror_urls(ctx, false)?;
+
+    Ok(CheckResult { sources, mirrors })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indexmap::indexmap;
+    use std::{env, fs};
+
+    use crate::DistroProfile;
+
+    /// A target root with the repository data of one mirror and branch
+    fn test_context(name: &str) -> Context {
+        let root = env::temp_dir().join(format!("apt-gen-list-{}-{}", name, std::process::id()));
+        let data = root.join("usr/share/distro-repository-data");
+        fs::create_dir_all(&data).unwrap();
+        fs::write(
+            data.join("mirrors.yml"),
+            "origin:\n  desc: AOSC main repository\n  url: https://repo.aosc.io/\n",
+        )
+        .unwrap();
+        fs::write(
+            data.join("branches.yml"),
+            "stable:\n  desc: Stable\n  suites: [stable]\n",
+        )
+        .unwrap();
+
+        Context::new(root, true, false, Some(DistroProfile::Aosc))
+    }
+
+    fn status(mirror_url: &str) -> Status {
+        Status {
+            branch: "stable".to_string(),
+            component: vec!["main".to_string()],
+            mirror: indexmap! {
+                "origin".to_string() => mirror_url.to_string(),
+            },
+            source_format: SourceFormat::OneLine,
+            signed_by: None,
+        }
+    }
+
+    /// Write the sources `status` generates, like an apply does
+    fn write_sources(ctx: &Context, status: &Status) {
+        let path = ctx.path(SourceFormat::OneLine.path());
+        let content = gen_sources_file_string(ctx, status, SourceFormat::OneLine).unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content.unwrap()).unwrap();
+    }
+
+    #[test]
+    fn check_clean() {
+        let ctx = test_context("check-clean");
+        let status_without_slash = status("https://repo.aosc.io");
+        let status = status("https://repo.aosc.io/");
+        write_sources(&ctx, &status);
+        let result = check_status(&ctx, &status).unwrap();
+        // A trailing slash is not a change of the URL
+        let without_slash = check_status(&ctx, &status_without_slash);
+        fs::remove_dir_all(ctx.path("/")).unwrap();
+
+        assert!(result.is_clean());
+        assert!(without_slash.unwrap().mirrors.is_empty());
+    }
+
+    #[test]
+    fn check_edited_sources() {
+        let ctx = test_context("check-edited");
+        let status = status("https://repo.aosc.io/");
+        write_sources(&ctx, &status);
+        let path = ctx.path(SourceFormat::OneLine.path());
+        let edited = fs::read_to_string(&path)
+            .unwrap()
+            .replace("stable main", "stable main testing");
+        fs::write(&path, &edited).unwrap();
+        let result = check_status(&ctx, &status).unwrap();
+        fs::remove_dir_all(ctx.path("/")).unwrap();
+
+        assert!(!result.is_clean());
+        assert_eq!(result.sources.len(), 1);
+        assert_eq!(result.sources[0].path, path);
+        assert!(result.mirrors.is_empty());
+    }
+
+    #[test]
+    fn check_mirror_drift() {
+        let ctx = test_context("check-drift");
+        let mut status = status("https://old.aosc.io/");
+        status
+            .mirror
+            .insert("gone".to_string(), "https://gone.example.com/".to_string());
+        write_sources(&ctx, &status);
+        let result = check_status(&ctx, &status).unwrap();
+        fs::remove_dir_all(ctx.path("/")).unwrap();
+
+        // The sources match the status, only its URLs are out of date
+        assert!(result.sources.is_empty());
+        assert_eq!(result.mirrors.len(), 2);
+        assert_eq!(result.mirrors[0].url, "https://old.aosc.io/");
+        assert!(matches!(
+            &result.mirrors[0].reason,
+            MirrorDriftReason::UrlChanged { expected_url } if expected_url == "https://repo.aosc.io/"
+        ));
+        assert_eq!(result.mirrors[1].name, "gone");
+        assert!(matches!(
+            result.mirrors[1].reason,
+            MirrorDriftReason::Removed
+        ));
+    }
+}
@@ -1,4 +1,107 @@
-use clap::{Command, Arg};
+use clap::{Arg, Command};
+use std::time::Duration;
+
+/// Longest timeout or deadline accepted, the timers cannot wait much longer
+const MAX_SECONDS: f64 = 24.0 * 60.0 * 60.0;
+
+/// Parse an age such as `90`, `30m`, `12h`, `1d` or `2w`, in seconds without a unit
+pub fn parse_age(age: &str) -> Result<Duration, String> {
+    let (number, unit) = match age.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => age.split_at(index),
+        None => (age, "s"),
+    };
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown unit `{}`, expected s, m, h, d or w", unit)),
+    };
+    let number = number.parse::<u64>().map_err(|e| e.to_string())?;
+    let seconds = number
+        .checked_mul(seconds)
+        .ok_or_else(|| format!("`{}` is too long", age))?;
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parse a number of seconds such as `10` or `2.5`, up to a day
+pub fn parse_seconds(seconds: &str) -> Result<Duration, String> {
+    let number = seconds.parse::<f64>().map_err(|e| e.to_string())?;
+    if !(0.0..=MAX_SECONDS).contains(&number) {
+        return Err(format!("expected 0 to {} seconds", MAX_SECONDS));
+    }
+
+    Ok(Duration::from_secs_f64(number))
+}
+
+/// Parse a number of mirrors, at least one
+pub fn parse_count(count: &str) -> Result<usize, String> {
+    match count.parse::<usize>().map_err(|e| e.to_string())? {
+        0 => Err("expected at least 1 mirror".to_string()),
+        count => Ok(count),
+    }
+}
+
+/// Parse a margin in percent, such as `10` or `2.5`
+pub fn parse_margin(margin: &str) -> Result<f64, String> {
+    let number = margin.parse::<f64>().map_err(|e| e.to_string())?;
+    if !number.is_finite() || number < 0.0 {
+        return Err("expected a percentage of 0 or more".to_string());
+    }
+
+    Ok(number)
+}
+
+/// Arguments shared by `speedtest` and `set-fastest-mirror-as-default`
+fn speedtest_args() -> [Arg<'static>; 8] {
+    [
+        Arg::new("parallel")
+            .help("Test mirror performance concurrently, test will take a shorter amount of time, but results will only serve as a rough estimate and could vary between runs")
+            .long("parallel")
+            .short('p'),
+        Arg::new("concurrency")
+            .help("Test at most N mirrors at the same time, implies --parallel")
+            .long("concurrency")
+            .short('j')
+            .value_name("N")
+            .takes_value(true),
+        Arg::new("top")
+            .help("Only test the download speed of the N mirrors with the lowest latency")
+            .long("top")
+            .short('t')
+            .value_name("N")
+            .takes_value(true),
+        Arg::new("connect-timeout")
+            .help("Seconds allowed to connect to a mirror")
+            .long("connect-timeout")
+            .value_name("SECONDS")
+            .default_value("10")
+            .validator(parse_seconds)
+            .takes_value(true),
+        Arg::new("read-timeout")
+            .help("Seconds a mirror may take to respond or to send more data")
+            .long("read-timeout")
+            .value_name("SECONDS")
+            .default_value("10")
+            .validator(parse_seconds)
+            .takes_value(true),
+        Arg::new("deadline")
+            .help("Stop testing after SECONDS and rank mirrors by what was measured so far")
+            .long("deadline")
+            .value_name("SECONDS")
+            .validator(parse_seconds)
+            .takes_value(true),
+        Arg::new("enabled")
+            .help("Only test the mirrors currently in use")
+            .long("enabled")
+            .conflicts_with("include-custom"),
+        Arg::new("include-custom")
+            .help("Also test custom mirrors")
+            .long("include-custom"),
+    ]
+}
 
 /// Build the CLI instance
 pub fn build_cli() -> Command<'static> {
@@ -9,6 +112,46 @@ pub fn build_cli() -> Command<'static> {
             "Utility for generating APT sources.list from available repository configurations."
         )
         .arg_required_else_help(true)
+        .arg(
+            Arg::new("root")
+                .help("Operate on the system installed in ROOT instead of the host")
+                .long("root")
+                .value_name("ROOT")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .help("Show a diff of the files that would be changed without writing anything")
+                .long("dry-run")
+                .global(true),
+        )
+        .arg(
+            Arg::new("no-refresh")
+                .help("Do not refresh the package lists after applying changes")
+                .long("no-refresh")
+                .global(true),
+        )
+        .arg(
+            Arg::new("profile")
+                .help("Distro profile of the target system, detected from os-release by default")
+                .long("profile")
+                .value_name("PROFILE")
+                .possible_values(["aosc", "aosc-retro", "debian"])
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::new("output")
+                .help("Output format of status, list-mirrors and speedtest")
+                .long("output")
+                .short('o')
+                .value_name("FORMAT")
+                .possible_values(["text", "json", "yaml"])
+                .default_value("text")
+                .takes_value(true)
+                .global(true),
+        )
         .subcommand(
             Command::new("set-branch")
                 .about("Set APT repository branch (e.g., stable)")
@@ -20,6 +163,24 @@ pub fn build_cli() -> Command<'static> {
                         .takes_value(true),
                 ),
         )
+        .subcommand(
+            Command::new("set-format")
+                .about("Set format of the generated APT sources")
+                .arg(
+                    Arg::new("FORMAT")
+                        .help("one-line (/etc/apt/sources.list) or deb822 (/etc/apt/sources.list.d/apt-gen-list.sources)")
+                        .possible_values(["one-line", "deb822"])
+                        .required(true)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("signed-by")
+                        .help("Keyring the generated sources are signed by, an empty KEYRING stops referencing one")
+                        .value_name("KEYRING")
+                        .long("signed-by")
+                        .takes_value(true),
+                ),
+        )
         .subcommand(
             Command::new("set-mirror")
                 .about("Set APT repository mirror")
@@ -127,25 +288,132 @@ pub fn build_cli() -> Command<'static> {
             Command::new("speedtest")
                 .about("Run speed-test on available mirrors")
                 .arg(
-                    Arg::new("parallel")
-                    .help("Test mirror performance concurrently, test will take a shorter amount of time, but results will only serve as a rough estimate and could vary between runs")
-                    .long("parallel")
-                    .short('p')
+                    Arg::new("rounds")
+                        .help("Test each mirror N times and rank mirrors by their median speed")
+                        .long("rounds")
+                        .short('r')
+                        .value_name("N")
+                        .default_value("1")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("MIRROR")
+                        .help("Mirrors to test, or URLs to test before adding them as custom mirrors")
+                        .conflicts_with_all(&["enabled", "include-custom"])
+                        .multiple_values(true)
+                        .takes_value(true),
+                )
+                .args(speedtest_args())
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Create the status from the APT sources currently in use")
+                .arg(
+                    Arg::new("apply")
+                        .help("Also regenerate the APT sources from the imported status")
+                        .long("apply"),
+                ),
+        )
+        .subcommand(
+            Command::new("regenerate")
+                .about("Update mirror URLs from the repository data and regenerate the APT sources")
+                .arg(
+                    Arg::new("drop-missing")
+                        .help("Disable mirrors which are no longer defined instead of keeping their stored URL")
+                        .long("drop-missing"),
+                ),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Check that the APT sources and mirror URLs still match the configuration")
+        )
+        .subcommand(
+            Command::new("freshness")
+                .about("Check how far mirrors lag behind the origin mirror")
+                .arg(
+                    Arg::new("enabled")
+                        .help("Only check the mirrors currently in use")
+                        .long("enabled"),
                 )
         )
+        .subcommand(
+            Command::new("history")
+                .about("Show previously applied configurations")
+        )
+        .subcommand(
+            Command::new("undo")
+                .about("Restore the configuration in effect before the latest change")
+        )
+        .subcommand(
+            Command::new("revert")
+                .about("Restore a configuration from history")
+                .arg(
+                    Arg::new("ID")
+                        .help("History entry ID, as shown by `apt-gen-list history`")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
         .subcommand(
             Command::new("list-mirrors")
                 .about("Show available mirror list")
+                .arg(
+                    Arg::new("speed")
+                        .help("Show the speed measured by the latest speed test and sort by it")
+                        .long("speed")
+                        .short('s'),
+                )
         )
         .subcommand(
             Command::new("set-fastest-mirror-as-default")
                 .about("Set fastest mirror as default")
+                .arg(
+                    Arg::new("rounds")
+                        .help("Test each mirror N times and pick the one with the best median speed")
+                        .long("rounds")
+                        .short('r')
+                        .value_name("N")
+                        .default_value("1")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("count")
+                        .help("Enable the N fastest mirrors, fastest first")
+                        .long("count")
+                        .short('n')
+                        .value_name("N")
+                        .default_value("1")
+                        .validator(parse_count)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("margin")
+                        .help("Keep the current mirror first unless the fastest one is more than PERCENT faster")
+                        .long("margin")
+                        .value_name("PERCENT")
+                        .validator(parse_margin)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("max-lag")
+                        .help("Skip mirrors lagging behind the origin mirror by more than AGE (e.g. 12h, 2d)")
+                        .long("max-lag")
+                        .value_name("AGE")
+                        .validator(parse_age)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("max-age")
+                        .help("Reuse speed test results younger than AGE (e.g. 30m, 12h, 1d) instead of testing again")
+                        .long("max-age")
+                        .value_name("AGE")
+                        .validator(parse_age)
+                        .takes_value(true),
+                )
+                .args(speedtest_args())
+        )
+        .subcommand(
+            Command::new("reset-mirror")
+                .about("Reset mirror to the default of the distro profile")
         )
-        .subcommands({
-            if cfg!(feature = "aosc") {
-                vec![Command::new("reset-mirror").about("Reset mirror to default")]
-            } else {
-                vec![]
-            }
-        })
 }
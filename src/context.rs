@@ -0,0 +1,208 @@
+use os_release::OsRelease;
+use std::{
+    cell::RefCell,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{transaction::Lock, DistroProfile, Error, Result};
+
+const STATUS_FILE: &str = "/var/lib/apt/gen/status.json";
+const HISTORY_FILE: &str = "/var/lib/apt/gen/history.json";
+const TRANSACTION_JOURNAL: &str = "/var/lib/apt/gen/transaction.json";
+const LOCK_FILE: &str = "/var/lib/apt/gen/lock";
+const SPEEDTEST_CACHE: &str = "/var/lib/apt/gen/speedtest.json";
+const CUSTOM_MIRROR_FILE: &str = "/etc/apt-gen-list/custom_mirror.yml";
+const SPEEDTEST_CONFIG_FILE: &str = "/etc/apt-gen-list/speedtest.yml";
+const REPO_DATA_DIRECTORY: &str = "/usr/share/distro-repository-data/";
+const LOCAL_REPO_DATA_DIRECTORY: &str = "/usr/local/share/distro-repository-data/";
+const OS_RELEASE_FILES: &[&str] = &["/etc/os-release", "/usr/lib/os-release"];
+const BIN_DIRECTORIES: &[&str] = &["/usr/bin", "/bin", "/usr/sbin", "/sbin"];
+
+/// The system apt-gen-list operates on, either the host or an alternate root
+pub struct Context {
+    root: PathBuf,
+    dry_run: bool,
+    refresh: bool,
+    profile: Option<DistroProfile>,
+    /// Held from [`Context::lock`] until the context is dropped
+    lock: RefCell<Option<Lock>>,
+}
+
+impl Context {
+    /// `profile` is detected from os-release of the target system if `None`
+    pub fn new<P: AsRef<Path>>(
+        root: P,
+        dry_run: bool,
+        refresh: bool,
+        profile: Option<DistroProfile>,
+    ) -> Self {
+        Context {
+            root: root.as_ref().to_path_buf(),
+            dry_run,
+            refresh,
+            profile,
+            lock: RefCell::new(None),
+        }
+    }
+
+    /// Wait until no other run changes the target system, and keep it that
+    /// way until the context is dropped
+    ///
+    /// Take the lock before reading the status, otherwise another run may
+    /// change it in between. [`apply_status`](crate::apply_status) takes it
+    /// if the caller did not.
+    pub fn lock(&self) -> Result<()> {
+        let mut lock = self.lock.borrow_mut();
+        if lock.is_none() {
+            *lock = Some(Lock::acquire(&self.lock_file())?);
+        }
+
+        Ok(())
+    }
+
+    /// Like [`Context::lock`] without waiting, returns whether the lock is held
+    pub fn try_lock(&self) -> Result<bool> {
+        let mut lock = self.lock.borrow_mut();
+        if lock.is_none() {
+            *lock = Lock::try_acquire(&self.lock_file())?;
+        }
+
+        Ok(lock.is_some())
+    }
+
+    /// Whether changes should only be computed instead of written
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Whether the package lists should be refreshed after applying changes
+    pub fn refresh(&self) -> bool {
+        self.refresh
+    }
+
+    /// Distro profile of the target system
+    pub fn profile(&self) -> Result<DistroProfile> {
+        match self.profile {
+            Some(profile) => Ok(profile),
+            None => Ok(DistroProfile::detect(&self.os_release()?)),
+        }
+    }
+
+    /// Whether the target system is the running host
+    pub fn is_host(&self) -> bool {
+        self.root == Path::new("/")
+    }
+
+    /// Map an absolute path of the target system onto the host filesystem
+    pub fn path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let path = path.as_ref();
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    pub fn status_file(&self) -> PathBuf {
+        self.path(STATUS_FILE)
+    }
+
+    pub fn history_file(&self) -> PathBuf {
+        self.path(HISTORY_FILE)
+    }
+
+    pub fn transaction_journal(&self) -> PathBuf {
+        self.path(TRANSACTION_JOURNAL)
+    }
+
+    /// Held by the run which is recovering or applying
+    pub fn lock_file(&self) -> PathBuf {
+        self.path(LOCK_FILE)
+    }
+
+    /// Latest speed test result of each mirror
+    pub fn speedtest_cache_file(&self) -> PathBuf {
+        self.path(SPEEDTEST_CACHE)
+    }
+
+    pub fn custom_mirror_file(&self) -> PathBuf {
+        self.path(CUSTOM_MIRROR_FILE)
+    }
+
+    /// Speed test probes configured by the administrator, overriding the
+    /// repository data
+    pub fn speedtest_config_file(&self) -> PathBuf {
+        self.path(SPEEDTEST_CONFIG_FILE)
+    }
+
+    pub fn repo_data_directory(&self) -> PathBuf {
+        let not_local_directory_path = self.path(REPO_DATA_DIRECTORY);
+        if not_local_directory_path.is_dir() {
+            not_local_directory_path
+        } else {
+            self.path(LOCAL_REPO_DATA_DIRECTORY)
+        }
+    }
+
+    pub fn repo_mirror_file(&self) -> PathBuf {
+        self.repo_data_directory().join("mirrors.yml")
+    }
+
+    pub fn repo_component_file(&self) -> PathBuf {
+        self.repo_data_directory().join("comps.yml")
+    }
+
+    pub fn repo_branch_file(&self) -> PathBuf {
+        self.repo_data_directory().join("branches.yml")
+    }
+
+    pub fn repo_speedtest_file(&self) -> PathBuf {
+        self.repo_data_directory().join("speedtest.yml")
+    }
+
+    pub fn os_release(&self) -> Result<OsRelease> {
+        for file in OS_RELEASE_FILES {
+            let mut path = self.path(file);
+            // An absolute symlink points into the target root, not into the host
+            if let Ok(target) = fs::read_link(&path) {
+                if target.is_absolute() {
+                    path = self.path(target);
+                }
+            }
+            if let Ok(os_release) = OsRelease::new_from(&path) {
+                return Ok(os_release);
+            }
+        }
+
+        Err(Error::OsReleaseNotFound(self.root.clone()))
+    }
+
+    /// Build a command running `program` on the target system, returns `None`
+    /// if the alternate root does not ship it
+    pub fn command(&self, program: &str) -> Option<Command> {
+        if !self.is_host()
+            && !BIN_DIRECTORIES
+                .iter()
+                .any(|dir| self.path(dir).join(program).is_file())
+        {
+            return None;
+        }
+
+        Some(self.command_at(program))
+    }
+
+    /// Build a command running the executable at `path` of the target system
+    pub fn command_at<S: AsRef<OsStr>>(&self, path: S) -> Command {
+        if self.is_host() {
+            return Command::new(path);
+        }
+        let mut command = Command::new("chroot");
+        command.arg(&self.root).arg(path);
+
+        command
+    }
+}
+
+/// Whether apt-gen-list runs with root privileges
+pub fn is_root() -> bool {
+    nix::unistd::geteuid().is_root()
+}
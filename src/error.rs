@@ -0,0 +1,314 @@
+use std::{fmt, io, path::PathBuf, process::ExitStatus};
+
+use crate::fl;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong in apt-gen-list
+///
+/// Each variant maps to a stable process exit code, see [`Error::exit_code`].
+#[derive(Debug)]
+pub enum Error {
+    /// The branch is not defined in the repository data
+    BranchNotFound(String),
+    /// The component is not defined in the repository data
+    ComponentNotFound(String),
+    /// The mirror is neither in the repository data nor a custom mirror
+    MirrorNotFound(String),
+    CustomMirrorNotFound(String),
+    FormatNotFound(String),
+    HistoryEntryNotFound(u64),
+    /// No entry of the APT sources matches the repository data
+    NothingToImport,
+    /// A custom mirror would shadow a mirror from the repository data
+    CustomMirrorNameConflict(String),
+    CustomMirrorNotUrl(String),
+    /// The custom mirror URL ends with the repository directory
+    DebsPathInUrl,
+    NoDeleteOnlyMirror,
+    NoDeleteOnlyComponent,
+    NothingToUndo,
+    /// The distro profile has no default status to reset to
+    NoDefaultStatus(String),
+    /// Neither the configuration, the repository data nor the distro profile
+    /// define a file to test mirrors with
+    NoSpeedtestProbe,
+    /// `check` found the sources or mirror URLs out of date
+    DriftDetected,
+    StatusFileNotFound(PathBuf),
+    StatusFileCorrupt(PathBuf),
+    /// The status file was written by a newer version of apt-gen-list
+    StatusFileTooNew {
+        path: PathBuf,
+        version: u32,
+    },
+    /// The status file cannot be migrated from `version`, it is left as is
+    StatusMigrationFailed {
+        path: PathBuf,
+        version: u32,
+        source: Box<Error>,
+    },
+    /// `/etc/apt/sources.list` has a begin marker but no end marker, so the
+    /// managed block cannot be told apart from the user's entries
+    UnterminatedManagedBlock(PathBuf),
+    /// A file from the repository data or the configuration cannot be read
+    ReadFile {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// A file from the repository data or the configuration is malformed
+    ParseFile {
+        path: PathBuf,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The status refers to a branch which has no suites in the repository data
+    BranchDataError(String),
+    InvalidUrl(url::ParseError),
+    OsReleaseNotFound(PathBuf),
+    /// The custom mirror cannot be reached
+    DownloadMirrorMetadataFailed(reqwest::Error),
+    /// Test data downloaded from a mirror does not match its checksum
+    MirrorDownloadFailed(String),
+    /// Every mirror failed the speed test
+    MirrorTestFailed,
+    /// The overall deadline of the speed test passed
+    DeadlineExceeded,
+    /// No mirror which passed the speed test is recent enough
+    MirrorsLagging,
+    Network(reqwest::Error),
+    CommandFailed {
+        command: String,
+        status: ExitStatus,
+    },
+    /// A hook at this path of the target system failed
+    HookFailed {
+        hook: PathBuf,
+        status: ExitStatus,
+    },
+    InvalidPath(PathBuf),
+    Io(io::Error),
+    Serialize(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Error {
+    /// Process exit code reported by the `apt-gen-list` command
+    ///
+    /// | Code | Meaning |
+    /// |------|---------|
+    /// | 10 | branch does not exist |
+    /// | 11 | component does not exist |
+    /// | 12 | mirror does not exist |
+    /// | 13 | custom mirror does not exist |
+    /// | 14 | sources format does not exist |
+    /// | 15 | history entry does not exist |
+    /// | 16 | no APT sources entry matches the repository data |
+    /// | 20 | custom mirror name is taken by the repository data |
+    /// | 21 | custom mirror URL is not a URL |
+    /// | 22 | custom mirror URL ends with the repository directory |
+    /// | 23 | refusing to remove the only mirror |
+    /// | 24 | refusing to remove the `main` component |
+    /// | 25 | nothing to undo |
+    /// | 26 | the distro profile has no default configuration |
+    /// | 27 | no speed test probe file is defined |
+    /// | 30 | status file does not exist |
+    /// | 31 | status file is corrupt |
+    /// | 32 | repository data or configuration cannot be read |
+    /// | 33 | repository data or configuration is malformed |
+    /// | 34 | os-release cannot be read |
+    /// | 35 | status file is from a newer version of apt-gen-list |
+    /// | 36 | status file cannot be migrated to the current version |
+    /// | 37 | the managed block of the sources file has no end marker |
+    /// | 40 | network failure |
+    /// | 41 | custom mirror cannot be reached |
+    /// | 42 | every mirror failed the speed test |
+    /// | 43 | the speed test deadline passed before any mirror was tested |
+    /// | 44 | no mirror which passed the speed test is recent enough |
+    /// | 50 | a command run on the target system failed |
+    /// | 51 | a hook failed |
+    /// | 60 | the sources or mirror URLs drifted from the status |
+    /// | 70 | internal error |
+    /// | 74 | other I/O error |
+    /// | 77 | permission denied, usually because apt-gen-list is not run as root |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::BranchNotFound(_) => 10,
+            Error::ComponentNotFound(_) => 11,
+            Error::MirrorNotFound(_) => 12,
+            Error::CustomMirrorNotFound(_) => 13,
+            Error::FormatNotFound(_) => 14,
+            Error::HistoryEntryNotFound(_) => 15,
+            Error::NothingToImport => 16,
+            Error::CustomMirrorNameConflict(_) => 20,
+            Error::CustomMirrorNotUrl(_) => 21,
+            Error::DebsPathInUrl => 22,
+            Error::NoDeleteOnlyMirror => 23,
+            Error::NoDeleteOnlyComponent => 24,
+            Error::NothingToUndo => 25,
+            Error::NoDefaultStatus(_) => 26,
+            Error::NoSpeedtestProbe => 27,
+            Error::StatusFileNotFound(_) => 30,
+            Error::StatusFileCorrupt(_) => 31,
+            Error::ReadFile { source, .. } if source.kind() == io::ErrorKind::PermissionDenied => {
+                77
+            }
+            Error::ReadFile { .. } => 32,
+            Error::ParseFile { .. } | Error::BranchDataError(_) | Error::InvalidUrl(_) => 33,
+            Error::OsReleaseNotFound(_) => 34,
+            Error::StatusFileTooNew { .. } => 35,
+            Error::StatusMigrationFailed { .. } => 36,
+            Error::UnterminatedManagedBlock(_) => 37,
+            Error::Network(_) | Error::MirrorDownloadFailed(_) => 40,
+            Error::DownloadMirrorMetadataFailed(_) => 41,
+            Error::MirrorTestFailed => 42,
+            Error::DeadlineExceeded => 43,
+            Error::MirrorsLagging => 44,
+            Error::CommandFailed { .. } => 50,
+            Error::HookFailed { .. } => 51,
+            Error::DriftDetected => 60,
+            Error::Serialize(_) => 70,
+            Error::Io(e) if e.kind() == io::ErrorKind::PermissionDenied => 77,
+            Error::Io(_) | Error::InvalidPath(_) => 74,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Error::BranchNotFound(_) => fl!("branch-not-found"),
+            Error::ComponentNotFound(comp) => fl!("comp-not-found", comp = comp.as_str()),
+            Error::MirrorNotFound(mirror) => fl!("mirror-not-found", mirror = mirror.as_str()),
+            Error::CustomMirrorNotFound(mirror) => {
+                fl!("custom-mirror-not-found", mirror = mirror.as_str())
+            }
+            Error::FormatNotFound(format) => fl!("format-not-found", format = format.as_str()),
+            Error::HistoryEntryNotFound(id) => {
+                let id = *id;
+                fl!("history-entry-not-found", id = id)
+            }
+            Error::NothingToImport => fl!("import-nothing"),
+            Error::CustomMirrorNameConflict(_) => fl!("custom-mirror-name-error"),
+            Error::CustomMirrorNotUrl(_) => fl!("custom-mirror-not-url"),
+            Error::DebsPathInUrl => fl!("debs-path-in-url"),
+            Error::NoDeleteOnlyMirror => fl!("no-delete-only-mirror"),
+            Error::NoDeleteOnlyComponent => fl!("no-delete-only-comp"),
+            Error::NothingToUndo => fl!("history-nothing-to-undo"),
+            Error::NoDefaultStatus(profile) => {
+                fl!("no-default-status", profile = profile.as_str())
+            }
+            Error::DriftDetected => fl!("check-drift"),
+            Error::NoSpeedtestProbe => fl!("no-speedtest-probe"),
+            Error::StatusFileNotFound(path) => {
+                fl!("status-file-not-found", path = path.display().to_string())
+            }
+            Error::StatusFileCorrupt(path) => {
+                fl!("status-file-read-error", path = path.display().to_string())
+            }
+            Error::StatusFileTooNew { path, version } => {
+                let version = *version;
+                fl!(
+                    "status-file-too-new",
+                    path = path.display().to_string(),
+                    version = version
+                )
+            }
+            Error::StatusMigrationFailed {
+                path,
+                version,
+                source,
+            } => {
+                let version = *version;
+                fl!(
+                    "status-migration-failed",
+                    path = path.display().to_string(),
+                    version = version,
+                    error = source.to_string()
+                )
+            }
+            Error::UnterminatedManagedBlock(path) => {
+                fl!("unterminated-block", path = path.display().to_string())
+            }
+            Error::ReadFile { path, source } => fl!(
+                "read-file-failed",
+                path = path.display().to_string(),
+                error = source.to_string()
+            ),
+            Error::ParseFile { path, source } => fl!(
+                "parse-file-failed",
+                path = path.display().to_string(),
+                error = source.to_string()
+            ),
+            Error::BranchDataError(_) => fl!("branch-data-error"),
+            Error::InvalidUrl(e) => fl!("invalid-url", error = e.to_string()),
+            Error::OsReleaseNotFound(path) => {
+                fl!("os-release-not-found", path = path.display().to_string())
+            }
+            Error::DownloadMirrorMetadataFailed(_) => fl!("download-mirror-metadata-failed"),
+            Error::MirrorDownloadFailed(mirror) => fl!("mirror-error", mirror = mirror.as_str()),
+            Error::MirrorTestFailed => fl!("mirror-test-failed"),
+            Error::DeadlineExceeded => fl!("speedtest-deadline-exceeded"),
+            Error::MirrorsLagging => fl!("mirrors-lagging"),
+            Error::Network(e) => fl!("network-error", error = e.to_string()),
+            Error::CommandFailed { command, status } => fl!(
+                "command-failed",
+                command = command.as_str(),
+                status = status.to_string()
+            ),
+            Error::HookFailed { hook, status } => fl!(
+                "hook-failed",
+                hook = hook.display().to_string(),
+                status = status.to_string()
+            ),
+            Error::InvalidPath(path) => fl!("invalid-path", path = path.display().to_string()),
+            Error::Io(e) => e.to_string(),
+            Error::Serialize(e) => e.to_string(),
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ReadFile { source, .. } => Some(source),
+            Error::ParseFile { source, .. } => Some(source.as_ref()),
+            Error::StatusMigrationFailed { source, .. } => Some(source.as_ref()),
+            Error::InvalidUrl(e) => Some(e),
+            Error::DownloadMirrorMetadataFailed(e) | Error::Network(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Serialize(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Network(e)
+    }
+}
+
+impl From<url::ParseError> for Error {
+    fn from(e: url::ParseError) -> Self {
+        Error::InvalidUrl(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Serialize(Box::new(e))
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(e: serde_yaml::Error) -> Self {
+        Error::Serialize(Box::new(e))
+    }
+}
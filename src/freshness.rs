@@ -0,0 +1,349 @@
+use chrono::{DateTime, Local};
+use futures::future;
+use indexmap::IndexMap;
+use reqwest::Client;
+use serde::Serialize;
+use std::{collections::HashMap, time::Duration};
+use tokio::runtime::Builder;
+
+use crate::{
+    get_branch_suites, get_directory_name, get_mirror_url, sources::debs_url, Context, MirrorScore,
+    Result, Status,
+};
+
+/// Time allowed to fetch one `InRelease` file
+const FRESHNESS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How far a mirror is behind, as found by [`check_freshness`]
+#[derive(Serialize)]
+pub struct MirrorFreshness {
+    pub name: String,
+    /// Seconds the mirror is behind the reference in its most outdated
+    /// suite, `None` if any suite could not be checked
+    pub lag: Option<i64>,
+    /// Whether the `Valid-Until` of any suite has passed, APT refuses such
+    /// a mirror
+    pub expired: bool,
+    pub suites: Vec<SuiteFreshness>,
+}
+
+/// The `InRelease` file of a suite on a mirror
+#[derive(Serialize)]
+pub struct SuiteFreshness {
+    pub suite: String,
+    /// `Date` field in seconds since the Unix epoch, `None` if the file could
+    /// not be fetched or has no date
+    pub date: Option<i64>,
+    /// `Valid-Until` field in seconds since the Unix epoch, if present
+    pub valid_until: Option<i64>,
+    /// Seconds behind the reference
+    pub lag: Option<i64>,
+    /// Why the file could not be checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A mirror dropped by [`exclude_lagging_mirrors`]
+pub struct ExcludedMirror {
+    pub name: String,
+    pub reason: ExclusionReason,
+}
+
+/// Why [`exclude_lagging_mirrors`] dropped a mirror
+pub enum ExclusionReason {
+    /// Some suite could not be checked
+    Unchecked,
+    /// The `Valid-Until` of some suite has passed
+    Expired,
+    /// Seconds the mirror is behind, more than allowed
+    Lagging(i64),
+}
+
+/// Fields of an `InRelease` file
+struct Release {
+    date: Option<i64>,
+    valid_until: Option<i64>,
+}
+
+/// Compare the `InRelease` file of each suite of the branch in `status` on
+/// `mirrors` with a reference
+///
+/// The reference of a suite is the origin mirror of the distro profile, or
+/// the newest `Date` of any mirror if the origin cannot be checked. Mirrors are
+/// returned in the order given.
+pub fn check_freshness(
+    ctx: &Context,
+    status: &Status,
+    mirrors: &IndexMap<String, String>,
+) -> Result<Vec<MirrorFreshness>> {
+    let suites = get_branch_suites(ctx, &status.branch)?;
+    let directory_name = get_directory_name(ctx)?;
+    let mut checked = mirrors.clone();
+    let origin = ctx.profile()?.origin_mirror();
+    if let Some(origin) = origin {
+        if let Ok(origin_url) = get_mirror_url(ctx, origin) {
+            checked.entry(origin.to_string()).or_insert(origin_url);
+        }
+    }
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    let client = Client::builder().timeout(FRESHNESS_TIMEOUT).build()?;
+    let mut requests = Vec::new();
+    for (mirror_name, mirror_url) in &checked {
+        let debs_url = debs_url(mirror_url, directory_name)?;
+        for suite in &suites {
+            let url = format!(
+                "{}/dists/{}/InRelease",
+                debs_url.as_str().trim_end_matches('/'),
+                suite
+            );
+            requests.push((mirror_name, suite, url));
+        }
+    }
+    let releases = runtime.block_on(future::join_all(
+        requests.iter().map(|(_, _, url)| get_release(&client, url)),
+    ));
+    let mut results = HashMap::new();
+    for ((mirror_name, suite, _), release) in requests.into_iter().zip(releases) {
+        results.insert((mirror_name.as_str(), suite.as_str()), release);
+    }
+    let mut references = HashMap::new();
+    for suite in &suites {
+        let date = |mirror_name: &str| {
+            results
+                .get(&(mirror_name, suite.as_str()))
+                .and_then(|release| release.as_ref().ok()?.date)
+        };
+        let reference = origin.and_then(date).or_else(|| {
+            checked
+                .keys()
+                .filter_map(|mirror_name| date(mirror_name))
+                .max()
+        });
+        references.insert(suite.as_str(), reference);
+    }
+    let now = Local::now().timestamp();
+    let mut freshness = Vec::new();
+    for mirror_name in mirrors.keys() {
+        let mut mirror_suites = Vec::new();
+        for suite in &suites {
+            let (release, error) = match results.remove(&(mirror_name.as_str(), suite.as_str())) {
+                Some(Ok(release)) => (Some(release), None),
+                Some(Err(e)) => (None, Some(e.to_string())),
+                None => (None, None),
+            };
+            let date = release.as_ref().and_then(|release| release.date);
+            mirror_suites.push(SuiteFreshness {
+                suite: suite.clone(),
+                date,
+                valid_until: release.and_then(|release| release.valid_until),
+                lag: date
+                    .zip(references[suite.as_str()])
+                    .map(|(date, reference)| (reference - date).max(0)),
+                error,
+            });
+        }
+        freshness.push(MirrorFreshness {
+            name: mirror_name.clone(),
+            lag: mirror_suites
+                .iter()
+                .map(|suite| suite.lag)
+                .collect::<Option<Vec<_>>>()
+                .and_then(|lags| lags.into_iter().max()),
+            expired: mirror_suites.iter().any(|suite| {
+                suite
+                    .valid_until
+                    .is_some_and(|valid_until| valid_until < now)
+            }),
+            suites: mirror_suites,
+        });
+    }
+
+    Ok(freshness)
+}
+
+/// Drop mirrors from a speed test table which lag behind by more than
+/// `max_lag`, have expired or cannot be checked, returns the dropped ones
+///
+/// Only mirrors which passed the speed test are checked, none of them may be
+/// left afterwards.
+pub fn exclude_lagging_mirrors(
+    ctx: &Context,
+    status: &Status,
+    mirrors_score_table: &mut Vec<MirrorScore>,
+    max_lag: Duration,
+) -> Result<Vec<ExcludedMirror>> {
+    let mut passed = IndexMap::new();
+    for score in mirrors_score_table.iter() {
+        if score.bytes_per_second.is_some() {
+            passed.insert(score.name.clone(), get_mirror_url(ctx, &score.name)?);
+        }
+    }
+    if passed.is_empty() {
+        return Ok(Vec::new());
+    }
+    let excluded = excluded_mirrors(check_freshness(ctx, status, &passed)?, max_lag);
+    mirrors_score_table.retain(|score| !excluded.iter().any(|mirror| mirror.name == score.name));
+
+    Ok(excluded)
+}
+
+/// Mirrors of `freshness` which [`exclude_lagging_mirrors`] drops
+fn excluded_mirrors(freshness: Vec<MirrorFreshness>, max_lag: Duration) -> Vec<ExcludedMirror> {
+    freshness
+        .into_iter()
+        .filter_map(|mirror| {
+            let reason = match mirror.lag {
+                None => ExclusionReason::Unchecked,
+                Some(_) if mirror.expired => ExclusionReason::Expired,
+                Some(lag) if lag as u64 > max_lag.as_secs() => ExclusionReason::Lagging(lag),
+                Some(_) => return None,
+            };
+
+            Some(ExcludedMirror {
+                name: mirror.name,
+                reason,
+            })
+        })
+        .collect()
+}
+
+async fn get_release(client: &Client, url: &str) -> Result<Release> {
+    let content = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    Ok(parse_release(&content))
+}
+
+/// Read the dates of an `InRelease` file, the signature around it is ignored
+fn parse_release(content: &str) -> Release {
+    let mut release = Release {
+        date: None,
+        valid_until: None,
+    };
+    for line in content.lines() {
+        // The fields end where the file list or the signature starts
+        if line.is_empty() && release.date.is_some() {
+            break;
+        }
+        match line.split_once(':') {
+            Some(("Date", value)) => release.date = parse_date(value),
+            Some(("Valid-Until", value)) => release.valid_until = parse_date(value),
+            _ => (),
+        }
+    }
+
+    release
+}
+
+/// Parse a date as written by APT archive tools, e.g. `Sat, 17 Oct 2026 08:23:31 UTC`
+fn parse_date(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let value = match value.strip_suffix("UTC") {
+        Some(value) => format!("{}+0000", value),
+        None => value.to_string(),
+    };
+
+    DateTime::parse_from_rfc2822(&value)
+        .ok()
+        .map(|date| date.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_date_formats() {
+        assert_eq!(
+            parse_date(" Sat, 17 Oct 2026 08:23:31 UTC"),
+            Some(1792225411)
+        );
+        assert_eq!(
+            parse_date("Sat, 17 Oct 2026 16:23:31 +0800"),
+            Some(1792225411)
+        );
+        assert_eq!(parse_date("17 Oct 2026"), None);
+    }
+
+    #[test]
+    fn parse_release_fields() {
+        let release = parse_release(
+            "-----BEGIN PGP SIGNED MESSAGE-----\n\
+             Hash: SHA256\n\
+             \n\
+             Origin: AOSC\n\
+             Date: Sat, 17 Oct 2026 08:23:31 UTC\n\
+             Valid-Until: Sat, 24 Oct 2026 08:23:31 UTC\n\
+             SHA256:\n\
+             \x20abc 123 main/binary-amd64/Packages\n\
+             \n\
+             -----BEGIN PGP SIGNATURE-----\n\
+             Date: Sun, 18 Oct 2026 08:23:31 UTC\n",
+        );
+
+        assert_eq!(release.date, Some(1792225411));
+        assert_eq!(release.valid_until, Some(1792225411 + 7 * 24 * 60 * 60));
+    }
+
+    fn freshness(name: &str, lag: Option<i64>, expired: bool) -> MirrorFreshness {
+        MirrorFreshness {
+            name: name.to_string(),
+            lag,
+            expired,
+            suites: vec![],
+        }
+    }
+
+    #[test]
+    fn exclude_lagging_unchecked_and_expired() {
+        let excluded = excluded_mirrors(
+            vec![
+                freshness("fresh", Some(0), false),
+                freshness("behind", Some(3600), false),
+                freshness("lagging", Some(3601), false),
+                freshness("unchecked", None, false),
+                freshness("expired", Some(0), true),
+            ],
+            Duration::from_secs(3600),
+        );
+        let excluded = excluded
+            .iter()
+            .map(|mirror| (mirror.name.as_str(), &mirror.reason))
+            .collect::<Vec<_>>();
+
+        assert!(matches!(
+            excluded[..],
+            [
+                ("lagging", ExclusionReason::Lagging(3601)),
+                ("unchecked", ExclusionReason::Unchecked),
+                ("expired", ExclusionReason::Expired),
+            ]
+        ));
+    }
+
+    #[test]
+    fn exclude_nothing_within_max_lag() {
+        let excluded = excluded_mirrors(
+            vec![
+                freshness("a", Some(0), false),
+                freshness("b", Some(60), false),
+            ],
+            Duration::from_secs(60),
+        );
+
+        assert!(excluded.is_empty());
+    }
+
+    #[test]
+    fn parse_release_without_dates() {
+        let release = parse_release("Origin: AOSC\nDate: soon\n");
+
+        assert_eq!(release.date, None);
+        assert_eq!(release.valid_until, None);
+    }
+}
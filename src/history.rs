@@ -0,0 +1,250 @@
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::{fs, io::ErrorKind, path::Path};
+
+use crate::{fl, Error, Result, Status};
+
+/// How many entries are kept in the history file
+const HISTORY_LIMIT: usize = 100;
+
+/// A status applied at some point in time
+#[derive(Deserialize, Serialize)]
+pub struct HistoryEntry {
+    pub id: u64,
+    /// Seconds since the Unix epoch
+    pub time: i64,
+    /// Arguments of the apt-gen-list invocation which applied this status,
+    /// `None` for the status found before any history was kept
+    pub command: Option<String>,
+    pub status: Status,
+    /// For an undo, the id of the entry whose status it restored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restored: Option<u64>,
+}
+
+impl HistoryEntry {
+    pub fn time_string(&self) -> String {
+        Local
+            .timestamp_opt(self.time, 0)
+            .single()
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn command_string(&self) -> String {
+        self.command
+            .clone()
+            .unwrap_or_else(|| fl!("history-initial"))
+    }
+}
+
+pub fn read_history(history_file: &Path) -> Result<Vec<HistoryEntry>> {
+    match fs::read(history_file) {
+        Ok(file) => serde_json::from_slice(&file).map_err(|e| Error::ParseFile {
+            path: history_file.to_path_buf(),
+            source: Box::new(e),
+        }),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(Error::ReadFile {
+            path: history_file.to_path_buf(),
+            source: e,
+        }),
+    }
+}
+
+/// Append `status` to the history and return the new content of the history file
+///
+/// The status currently on disk is recorded first if there is no history yet,
+/// so that the very first change can be undone as well. It is dated by when
+/// the status file was last written. `restored` is the
+/// entry an undo applies the status of.
+pub fn record_history(
+    history_file: &Path,
+    status_file: &Path,
+    status: &Status,
+    command: &str,
+    restored: Option<u64>,
+) -> Result<String> {
+    let mut history = read_history(history_file)?;
+    let now = Local::now().timestamp();
+    if history.is_empty() {
+        if let Some(previous) = fs::read(status_file)
+            .ok()
+            .and_then(|file| serde_json::from_slice::<Status>(&file).ok())
+        {
+            let time = fs::metadata(status_file)
+                .and_then(|metadata| metadata.modified())
+                .map(|modified| DateTime::<Local>::from(modified).timestamp())
+                .unwrap_or(now);
+            history.push(HistoryEntry {
+                id: 1,
+                time,
+                command: None,
+                status: previous,
+                restored: None,
+            });
+        }
+    }
+    let id = history.last().map(|entry| entry.id + 1).unwrap_or(1);
+    history.push(HistoryEntry {
+        id,
+        time: now,
+        command: Some(command.to_string()),
+        status: status.clone(),
+        restored,
+    });
+    if history.len() > HISTORY_LIMIT {
+        history.drain(..history.len() - HISTORY_LIMIT);
+    }
+
+    Ok(format!("{}\n", serde_json::to_string(&history)?))
+}
+
+/// Find the status to restore for `revert <id>`
+pub fn find_history_entry(history_file: &Path, id: u64) -> Result<Status> {
+    read_history(history_file)?
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .map(|entry| entry.status)
+        .ok_or(Error::HistoryEntryNotFound(id))
+}
+
+/// Find the entry in effect before the latest change
+///
+/// An undo continues from the entry it restored, so that repeated undos keep
+/// going back instead of undoing each other.
+pub fn find_undo_entry(history_file: &Path) -> Result<HistoryEntry> {
+    let mut history = read_history(history_file)?;
+    let position = match history.last() {
+        Some(HistoryEntry {
+            restored: Some(id), ..
+        }) => history.iter().position(|entry| entry.id == *id),
+        Some(_) => Some(history.len() - 1),
+        None => None,
+    };
+    match position {
+        Some(position) if position > 0 => Ok(history.swap_remove(position - 1)),
+        _ => Err(Error::NothingToUndo),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indexmap::IndexMap;
+    use std::{
+        env,
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+
+    use crate::SourceFormat;
+
+    fn status(branch: &str) -> Status {
+        Status {
+            branch: branch.to_string(),
+            component: vec!["main".to_string()],
+            mirror: IndexMap::new(),
+            source_format: SourceFormat::OneLine,
+            signed_by: None,
+        }
+    }
+
+    /// Paths of the history and status files in a new temporary directory
+    fn test_files(name: &str) -> (PathBuf, PathBuf) {
+        let dir = env::temp_dir().join(format!("apt-gen-list-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        (dir.join("history.json"), dir.join("status.json"))
+    }
+
+    /// Record `branch` like an apply does, which writes both files
+    fn apply(files: &(PathBuf, PathBuf), branch: &str, command: &str, restored: Option<u64>) {
+        let (history_file, status_file) = files;
+        let history = record_history(
+            history_file,
+            status_file,
+            &status(branch),
+            command,
+            restored,
+        )
+        .unwrap();
+        fs::write(history_file, history).unwrap();
+        fs::write(status_file, serde_json::to_string(&status(branch)).unwrap()).unwrap();
+    }
+
+    fn undo(files: &(PathBuf, PathBuf)) -> Result<String> {
+        let entry = find_undo_entry(&files.0)?;
+        apply(files, &entry.status.branch, "undo", Some(entry.id));
+
+        Ok(entry.status.branch)
+    }
+
+    #[test]
+    fn initial_entry_keeps_status_time() {
+        let files = test_files("history-initial");
+        fs::write(&files.1, serde_json::to_string(&status("stable")).unwrap()).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&files.1)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        apply(&files, "testing", "set-branch testing", None);
+        let history = read_history(&files.0).unwrap();
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].time, 1_000_000);
+        assert!(history[0].command.is_none());
+        assert_eq!(history[0].status.branch, "stable");
+        assert!(history[1].time > 1_000_000);
+        fs::remove_dir_all(files.0.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn undo_undo_revert() {
+        let files = test_files("history-undo");
+        fs::write(&files.1, serde_json::to_string(&status("a")).unwrap()).unwrap();
+        apply(&files, "b", "set-branch b", None);
+        apply(&files, "c", "set-branch c", None);
+
+        // Repeated undos keep going back instead of undoing each other
+        assert_eq!(undo(&files).unwrap(), "b");
+        assert_eq!(undo(&files).unwrap(), "a");
+        assert!(matches!(undo(&files), Err(Error::NothingToUndo)));
+
+        // A revert is a change of its own, an undo goes back to before it
+        let reverted = find_history_entry(&files.0, 3).unwrap();
+        assert_eq!(reverted.branch, "c");
+        apply(&files, &reverted.branch, "revert 3", None);
+        assert_eq!(undo(&files).unwrap(), "a");
+        // Then further back through the entries before the revert
+        assert_eq!(undo(&files).unwrap(), "b");
+
+        let history = read_history(&files.0).unwrap();
+        let ids = history
+            .iter()
+            .map(|entry| (entry.id, entry.restored))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            [
+                (1, None),
+                (2, None),
+                (3, None),
+                (4, Some(2)),
+                (5, Some(1)),
+                (6, None),
+                (7, Some(5)),
+                (8, Some(4)),
+            ]
+        );
+        assert!(matches!(
+            find_history_entry(&files.0, 9),
+            Err(Error::HistoryEntryNotFound(9))
+        ));
+        fs::remove_dir_all(files.0.parent().unwrap()).unwrap();
+    }
+}
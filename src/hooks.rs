@@ -0,0 +1,107 @@
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use crate::{ApplyStep, Context, Error, Result};
+
+const HOOK_DIRECTORY: &str = "/etc/apt-gen-list/hooks";
+
+/// When hooks run during [`apply_status`](crate::apply_status)
+#[derive(Clone, Copy)]
+pub enum HookStage {
+    /// Before anything is written, a failing hook aborts the apply
+    PreApply,
+    /// After all changes are applied and the package lists are refreshed
+    PostApply,
+}
+
+impl HookStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookStage::PreApply => "pre-apply",
+            HookStage::PostApply => "post-apply",
+        }
+    }
+
+    /// Directory of the hooks of this stage on the target system
+    fn directory(&self) -> PathBuf {
+        Path::new(HOOK_DIRECTORY).join(format!("{}.d", self.name()))
+    }
+}
+
+/// Hooks of `stage` on the target system, in the order they run
+///
+/// Like run-parts(8), only executables whose names consist of ASCII letters,
+/// digits, underscores and hyphens are run, so that files such as
+/// `foo.dpkg-old` are skipped.
+pub fn find_hooks(ctx: &Context, stage: HookStage) -> Result<Vec<PathBuf>> {
+    let directory = ctx.path(stage.directory());
+    let entries = match fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(Error::ReadFile {
+                path: directory,
+                source: e,
+            })
+        }
+    };
+    let mut hooks = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let is_valid_name = entry.file_name().to_str().is_some_and(|name| {
+            name.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        });
+        let is_executable = fs::metadata(entry.path())
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0);
+        if is_valid_name && is_executable {
+            hooks.push(stage.directory().join(entry.file_name()));
+        }
+    }
+    hooks.sort();
+
+    Ok(hooks)
+}
+
+/// Run every hook of `stage`, passing the status as JSON on stdin
+///
+/// Hooks also get `APT_GEN_LIST_HOOK` set to the name of the stage and
+/// `APT_GEN_LIST_SOURCES_FILE` set to the generated sources file.
+pub(crate) fn run_hooks<F: FnMut(ApplyStep)>(
+    ctx: &Context,
+    stage: HookStage,
+    status_str: &str,
+    source_file: &str,
+    on_step: &mut F,
+) -> Result<()> {
+    for hook in find_hooks(ctx, stage)? {
+        on_step(ApplyStep::RunHook(&hook));
+        let mut child = ctx
+            .command_at(&hook)
+            .env("APT_GEN_LIST_HOOK", stage.name())
+            .env("APT_GEN_LIST_SOURCES_FILE", source_file)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A hook is free not to read the status
+            match stdin.write_all(status_str.as_bytes()) {
+                Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(e.into()),
+                _ => (),
+            }
+        }
+        let exit_status = child.wait()?;
+        if !exit_status.success() {
+            return Err(Error::HookFailed {
+                hook,
+                status: exit_status,
+            });
+        }
+    }
+
+    Ok(())
+}
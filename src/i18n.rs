@@ -1,7 +1,6 @@
-use anyhow::Result;
 use i18n_embed::{
     fluent::{fluent_language_loader, FluentLanguageLoader},
-    DesktopLanguageRequester, LanguageLoader,
+    DesktopLanguageRequester, I18nEmbedError, LanguageLoader,
 };
 use lazy_static::lazy_static;
 use rust_embed::RustEmbed;
@@ -27,7 +26,7 @@ lazy_static! {
 #[folder = "i18n"]
 struct Localizations;
 
-fn load_i18n() -> Result<FluentLanguageLoader> {
+fn load_i18n() -> Result<FluentLanguageLoader, I18nEmbedError> {
     let language_loader: FluentLanguageLoader = fluent_language_loader!();
     let requested_languages = DesktopLanguageRequester::requested_languages();
     let fallback_language: &[LanguageIdentifier] = &["en-US".parse().unwrap()];
@@ -36,6 +35,8 @@ fn load_i18n() -> Result<FluentLanguageLoader> {
         .chain(fallback_language.iter())
         .collect();
     language_loader.load_languages(&Localizations, &languages)?;
+    // Isolation marks would end up in error messages of machine-readable output
+    language_loader.set_use_isolating(false);
 
     Ok(language_loader)
 }
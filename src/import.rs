@@ -0,0 +1,518 @@
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::{
+    fmt, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    fl, list_mirrors, read_distro_file,
+    sources::{blank_managed_block, debs_url, is_same_url},
+    AvailableMirror, BranchesData, ComponentData, Context, Error, Result, SourceFormat, Status,
+};
+
+const SOURCES_LIST: &str = "/etc/apt/sources.list";
+const SOURCES_LIST_DIRECTORY: &str = "/etc/apt/sources.list.d";
+
+/// Status reconstructed from the APT sources of the target system
+#[derive(Serialize)]
+pub struct ImportResult {
+    pub status: Status,
+    /// Entries which are in use but cannot be expressed in the status
+    pub unmapped: Vec<UnmappedEntry>,
+}
+
+/// A sources entry, or part of one, which does not match the repository data
+#[derive(Serialize)]
+pub struct UnmappedEntry {
+    /// Path of the sources file on the target system
+    pub path: PathBuf,
+    /// Line the entry starts at
+    pub line: usize,
+    #[serde(flatten)]
+    pub reason: UnmappedReason,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum UnmappedReason {
+    /// The line or stanza cannot be parsed
+    Malformed,
+    /// `deb-src` entries are never generated
+    SourcePackages,
+    UnknownMirror {
+        uri: String,
+    },
+    /// The suite is not part of the inferred branch
+    UnknownSuite {
+        suite: String,
+    },
+    UnknownComponent {
+        component: String,
+    },
+}
+
+impl fmt::Display for UnmappedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            UnmappedReason::Malformed => fl!("import-malformed"),
+            UnmappedReason::SourcePackages => fl!("import-source-packages"),
+            UnmappedReason::UnknownMirror { uri } => {
+                fl!("import-unknown-mirror", uri = uri.as_str())
+            }
+            UnmappedReason::UnknownSuite { suite } => {
+                fl!("import-unknown-suite", suite = suite.as_str())
+            }
+            UnmappedReason::UnknownComponent { component } => {
+                fl!("import-unknown-component", comp = component.as_str())
+            }
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+/// An entry of a sources file, in either format
+pub(crate) struct SourceEntry {
+    pub path: PathBuf,
+    pub line: usize,
+    pub format: SourceFormat,
+    pub types: Vec<String>,
+    pub uris: Vec<String>,
+    pub suites: Vec<String>,
+    pub components: Vec<String>,
+    pub signed_by: Option<String>,
+}
+
+impl SourceEntry {
+    fn unmapped(&self, reason: UnmappedReason) -> UnmappedEntry {
+        UnmappedEntry {
+            path: self.path.clone(),
+            line: self.line,
+            reason,
+        }
+    }
+}
+
+/// Reconstruct a status from the APT sources of the target system
+///
+/// Every `deb` entry in `/etc/apt/sources.list` and `/etc/apt/sources.list.d/`
+/// is matched against the mirrors and components from the repository data
+/// and the custom mirror file. The branch is the one with the most suites
+/// which are all in use.
+pub fn import_sources(ctx: &Context) -> Result<ImportResult> {
+    let directory_name = ctx.profile()?.repo_directory();
+    let mirrors = list_mirrors(ctx)?;
+    let branches = read_distro_file::<BranchesData, _>(ctx.repo_branch_file())?;
+    let comps = read_distro_file::<ComponentData, _>(ctx.repo_component_file())?;
+    let mut unmapped = Vec::new();
+    let entries = read_source_entries(ctx, false, &mut unmapped)?;
+
+    let mut mirror = IndexMap::new();
+    let mut component = Vec::new();
+    let mut suites: Vec<(&SourceEntry, &str)> = Vec::new();
+    let mut source_format = SourceFormat::OneLine;
+    let mut signed_by = None;
+    for entry in &entries {
+        if entry.types.iter().any(|kind| kind != "deb") {
+            unmapped.push(entry.unmapped(UnmappedReason::SourcePackages));
+        }
+        if !entry.types.iter().any(|kind| kind == "deb") {
+            continue;
+        }
+        let mut is_mapped = false;
+        for uri in &entry.uris {
+            match find_mirror(&mirrors, directory_name, uri) {
+                Some(found) => {
+                    mirror
+                        .entry(found.name.clone())
+                        .or_insert_with(|| found.url.clone());
+                    is_mapped = true;
+                }
+                None => unmapped
+                    .push(entry.unmapped(UnmappedReason::UnknownMirror { uri: uri.clone() })),
+            }
+        }
+        if !is_mapped {
+            continue;
+        }
+        if entry.format == SourceFormat::Deb822 {
+            source_format = SourceFormat::Deb822;
+        }
+        if signed_by.is_none() {
+            signed_by = entry.signed_by.clone();
+        }
+        suites.extend(entry.suites.iter().map(|suite| (entry, suite.as_str())));
+        for comp in &entry.components {
+            if !comps.contains_key(comp) {
+                unmapped.push(entry.unmapped(UnmappedReason::UnknownComponent {
+                    component: comp.clone(),
+                }));
+            } else if !component.contains(comp) {
+                component.push(comp.clone());
+            }
+        }
+    }
+    if mirror.is_empty() {
+        return Err(Error::NothingToImport);
+    }
+    let mut branch_names = branches.keys().collect::<Vec<_>>();
+    branch_names.sort();
+    let (branch, branch_info) = branch_names
+        .into_iter()
+        .map(|name| (name, &branches[name]))
+        .filter(|(_, info)| {
+            info.suites
+                .iter()
+                .all(|suite| suites.iter().any(|(_, used)| used == suite))
+        })
+        .max_by_key(|(_, info)| info.suites.len())
+        .ok_or(Error::NothingToImport)?;
+    for (entry, suite) in suites {
+        if !branch_info.suites.iter().any(|s| s == suite) {
+            unmapped.push(entry.unmapped(UnmappedReason::UnknownSuite {
+                suite: suite.to_string(),
+            }));
+        }
+    }
+    unmapped.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    // The main component can never be removed
+    if !component.iter().any(|comp| comp == "main") {
+        component.insert(0, "main".to_string());
+    }
+
+    Ok(ImportResult {
+        status: Status {
+            branch: branch.clone(),
+            component,
+            mirror,
+            source_format,
+            signed_by,
+        },
+        unmapped,
+    })
+}
+
+/// Parse every sources file of the target system, reporting what cannot be
+/// parsed to `unmapped`
+///
+/// With `unmanaged_only`, entries generated by apt-gen-list are left out.
+pub(crate) fn read_source_entries(
+    ctx: &Context,
+    unmanaged_only: bool,
+    unmapped: &mut Vec<UnmappedEntry>,
+) -> Result<Vec<SourceEntry>> {
+    let mut entries = Vec::new();
+    for path in source_files(ctx)? {
+        if unmanaged_only && path == Path::new(SourceFormat::Deb822.path()) {
+            continue;
+        }
+        let mut content = fs::read_to_string(ctx.path(&path)).map_err(|e| Error::ReadFile {
+            path: ctx.path(&path),
+            source: e,
+        })?;
+        if unmanaged_only && path == Path::new(SourceFormat::OneLine.path()) {
+            content = blank_managed_block(&content);
+        }
+        if path.extension().is_some_and(|ext| ext == "sources") {
+            entries.extend(parse_deb822(&path, &content, unmapped));
+        } else {
+            entries.extend(parse_one_line(&path, &content, unmapped));
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Sources files on the target system, as paths of the target system
+fn source_files(ctx: &Context) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if ctx.path(SOURCES_LIST).is_file() {
+        files.push(PathBuf::from(SOURCES_LIST));
+    }
+    let directory = ctx.path(SOURCES_LIST_DIRECTORY);
+    let entries = match fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(files),
+        Err(e) => {
+            return Err(Error::ReadFile {
+                path: directory,
+                source: e,
+            })
+        }
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name();
+        let name_str = name.to_string_lossy();
+        if name_str.ends_with(".list") || name_str.ends_with(".sources") {
+            names.push(name);
+        }
+    }
+    names.sort();
+    files.extend(
+        names
+            .into_iter()
+            .map(|name| Path::new(SOURCES_LIST_DIRECTORY).join(name)),
+    );
+
+    Ok(files)
+}
+
+/// Find the mirror whose repository URL is `uri`
+fn find_mirror<'a>(
+    mirrors: &'a [AvailableMirror],
+    directory_name: &str,
+    uri: &str,
+) -> Option<&'a AvailableMirror> {
+    mirrors.iter().find(|mirror| {
+        debs_url(&mirror.url, directory_name).is_ok_and(|debs_url| is_same_url(&debs_url, uri))
+    })
+}
+
+/// Parse `deb [options] uri suite [component...]` lines
+fn parse_one_line(
+    path: &Path,
+    content: &str,
+    unmapped: &mut Vec<UnmappedEntry>,
+) -> Vec<SourceEntry> {
+    let mut entries = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut tokens = line.split_whitespace().collect::<Vec<_>>();
+        let kind = tokens.remove(0);
+        let mut signed_by = None;
+        if tokens.first().is_some_and(|token| token.starts_with('[')) {
+            let Some(end) = tokens.iter().position(|token| token.ends_with(']')) else {
+                unmapped.push(UnmappedEntry {
+                    path: path.to_path_buf(),
+                    line: index + 1,
+                    reason: UnmappedReason::Malformed,
+                });
+                continue;
+            };
+            let options = tokens.drain(..=end).collect::<Vec<_>>().join(" ");
+            for option in options
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .split_whitespace()
+            {
+                if let Some(keyring) = option.strip_prefix("signed-by=") {
+                    signed_by = Some(keyring.to_string());
+                }
+            }
+        }
+        if tokens.len() < 2 {
+            unmapped.push(UnmappedEntry {
+                path: path.to_path_buf(),
+                line: index + 1,
+                reason: UnmappedReason::Malformed,
+            });
+            continue;
+        }
+        entries.push(SourceEntry {
+            path: path.to_path_buf(),
+            line: index + 1,
+            format: SourceFormat::OneLine,
+            types: vec![kind.to_string()],
+            uris: vec![tokens[0].to_string()],
+            suites: vec![tokens[1].to_string()],
+            components: tokens[2..].iter().map(|comp| comp.to_string()).collect(),
+            signed_by,
+        });
+    }
+
+    entries
+}
+
+/// Parse deb822 stanzas, skipping those with `Enabled: no`
+fn parse_deb822(path: &Path, content: &str, unmapped: &mut Vec<UnmappedEntry>) -> Vec<SourceEntry> {
+    let mut entries = Vec::new();
+    let mut fields: Vec<(String, String)> = Vec::new();
+    let mut start = 0;
+    // A trailing empty line ends the last stanza
+    for (index, line) in content.lines().chain([""]).enumerate() {
+        if line.starts_with('#') {
+            continue;
+        }
+        if line.trim().is_empty() {
+            if !fields.is_empty() {
+                let field = |name: &str| {
+                    fields
+                        .iter()
+                        .find(|(key, _)| key == name)
+                        .map(|(_, value)| value.as_str())
+                };
+                let values = |name: &str| {
+                    field(name)
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .map(str::to_string)
+                        .collect::<Vec<_>>()
+                };
+                let entry = SourceEntry {
+                    path: path.to_path_buf(),
+                    line: start,
+                    format: SourceFormat::Deb822,
+                    types: values("types"),
+                    uris: values("uris"),
+                    suites: values("suites"),
+                    components: values("components"),
+                    signed_by: field("signed-by").map(str::to_string),
+                };
+                if entry.types.is_empty() || entry.uris.is_empty() || entry.suites.is_empty() {
+                    unmapped.push(entry.unmapped(UnmappedReason::Malformed));
+                } else if field("enabled") != Some("no") {
+                    entries.push(entry);
+                }
+                fields.clear();
+            }
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if fields.is_empty() {
+            start = index + 1;
+        }
+        match line.split_once(':') {
+            Some((key, value)) => {
+                fields.push((key.trim().to_ascii_lowercase(), value.trim().to_string()))
+            }
+            None => unmapped.push(UnmappedEntry {
+                path: path.to_path_buf(),
+                line: index + 1,
+                reason: UnmappedReason::Malformed,
+            }),
+        }
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_one_line_options() {
+        let mut unmapped = Vec::new();
+        let entries = parse_one_line(
+            Path::new("/etc/apt/sources.list"),
+            "# comment\n\
+             deb [arch=amd64 signed-by=/usr/share/keyrings/aosc.gpg] https://repo.aosc.io/debs stable main bsp-sunxi\n\
+             deb-src https://repo.aosc.io/debs stable # trailing comment\n",
+            &mut unmapped,
+        );
+
+        assert!(unmapped.is_empty());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].line, 2);
+        assert_eq!(entries[0].types, ["deb"]);
+        assert_eq!(entries[0].uris, ["https://repo.aosc.io/debs"]);
+        assert_eq!(entries[0].suites, ["stable"]);
+        assert_eq!(entries[0].components, ["main", "bsp-sunxi"]);
+        assert_eq!(
+            entries[0].signed_by.as_deref(),
+            Some("/usr/share/keyrings/aosc.gpg")
+        );
+        assert_eq!(entries[1].types, ["deb-src"]);
+        assert!(entries[1].components.is_empty());
+    }
+
+    #[test]
+    fn parse_one_line_malformed() {
+        let mut unmapped = Vec::new();
+        let entries = parse_one_line(
+            Path::new("/etc/apt/sources.list"),
+            "deb [signed-by=/usr/share/keyrings/aosc.gpg https://repo.aosc.io/debs stable\n\
+             deb https://repo.aosc.io/debs\n",
+            &mut unmapped,
+        );
+
+        assert!(entries.is_empty());
+        assert_eq!(unmapped.len(), 2);
+        assert_eq!(unmapped[0].line, 1);
+        assert_eq!(unmapped[1].line, 2);
+        assert!(unmapped
+            .iter()
+            .all(|entry| matches!(entry.reason, UnmappedReason::Malformed)));
+    }
+
+    #[test]
+    fn parse_deb822_stanzas() {
+        let mut unmapped = Vec::new();
+        let entries = parse_deb822(
+            Path::new("/etc/apt/sources.list.d/aosc.sources"),
+            "# comment\n\
+             Types: deb\n\
+             URIs: https://repo.aosc.io/debs\n\
+             \x20     https://mirrors.tuna.tsinghua.edu.cn/anthon/debs\n\
+             Suites: stable\n\
+             Components: main\n\
+             Signed-By: /usr/share/keyrings/aosc.gpg\n\
+             \n\
+             Types: deb\n\
+             URIs: https://example.com/debs\n\
+             Suites: stable\n\
+             Enabled: no\n\
+             \n\
+             \n\
+             types: deb deb-src\n\
+             uris: https://repo.aosc.io/debs\n\
+             suites: stable-proposed\n",
+            &mut unmapped,
+        );
+
+        assert!(unmapped.is_empty());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].line, 2);
+        assert_eq!(
+            entries[0].uris,
+            [
+                "https://repo.aosc.io/debs",
+                "https://mirrors.tuna.tsinghua.edu.cn/anthon/debs"
+            ]
+        );
+        assert_eq!(entries[0].suites, ["stable"]);
+        assert_eq!(entries[0].components, ["main"]);
+        assert_eq!(
+            entries[0].signed_by.as_deref(),
+            Some("/usr/share/keyrings/aosc.gpg")
+        );
+        assert_eq!(entries[1].line, 15);
+        assert_eq!(entries[1].types, ["deb", "deb-src"]);
+        assert_eq!(entries[1].suites, ["stable-proposed"]);
+        assert!(entries[1].components.is_empty());
+    }
+
+    #[test]
+    fn parse_deb822_malformed() {
+        let mut unmapped = Vec::new();
+        let entries = parse_deb822(
+            Path::new("/etc/apt/sources.list.d/aosc.sources"),
+            "Types: deb\n\
+             Suites: stable\n\
+             \n\
+             Types: deb\n\
+             not a field\n\
+             URIs: https://repo.aosc.io/debs\n\
+             Suites: stable\n",
+            &mut unmapped,
+        );
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].line, 4);
+        assert_eq!(unmapped.len(), 2);
+        assert_eq!(unmapped[0].line, 1);
+        assert_eq!(unmapped[1].line, 5);
+    }
+}
@@ -0,0 +1,67 @@
+//! Generate APT sources from distro repository data
+//!
+//! This is the library behind the `apt-gen-list` command. It reads the
+//! repository data shipped in `distro-repository-data` (mirrors, branches and
+//! components), keeps the selected configuration as a [`Status`], renders it
+//! into APT sources and benchmarks mirrors. Nothing in here prints to the
+//! terminal: long-running operations report their progress through callbacks
+//! and everything else is returned to the caller.
+//!
+//! ```no_run
+//! use apt_gen_list::{apply_status, read_status, Context};
+//!
+//! let ctx = Context::new("/", false, true, None);
+//! ctx.lock()?;
+//! let mut status = read_status(&ctx)?;
+//! status.set_branch(&ctx, "testing")?;
+//! apply_status(&ctx, &status, "set-branch testing", |_| ())?;
+//! # Ok::<(), apt_gen_list::Error>(())
+//! ```
+
+mod apply;
+mod cache;
+mod check;
+mod context;
+mod error;
+mod freshness;
+mod history;
+mod hooks;
+mod i18n;
+mod import;
+mod profile;
+mod repo;
+mod sources;
+mod speedtest;
+mod status;
+mod transaction;
+
+pub use apply::{apply_status, apply_undo, recover_transaction, write_file, ApplyStep, FileChange};
+pub use cache::{cached_score_table, read_speedtest_cache, record_speedtest, CachedScore};
+pub use check::{check_status, CheckResult, MirrorDrift, MirrorDriftReason};
+pub use context::{is_root, Context};
+pub use error::{Error, Result};
+pub use freshness::{
+    check_freshness, exclude_lagging_mirrors, ExcludedMirror, ExclusionReason, MirrorFreshness,
+    SuiteFreshness,
+};
+pub use history::{find_history_entry, find_undo_entry, read_history, HistoryEntry};
+pub use hooks::{find_hooks, HookStage};
+#[doc(hidden)]
+pub use i18n::I18N_LOADER;
+pub use import::{import_sources, ImportResult, UnmappedEntry, UnmappedReason};
+pub use profile::DistroProfile;
+pub use repo::{
+    add_custom_mirror, get_branch_suites, get_directory_name, get_mirror_url, get_speedtest_probes,
+    list_mirrors, read_distro_file, remove_custom_mirror, AvailableMirror, BranchInfo,
+    BranchesData, ComponentData, CustomMirrorData, MirrorInfo, MirrorsData, SpeedtestData,
+    SpeedtestProbe,
+};
+pub use sources::{
+    find_duplicate_entries, gen_sources_file_string, gen_sources_list_string, DuplicateEntry,
+};
+pub use speedtest::{
+    fastest_mirror, fastest_mirror_confidence, fastest_mirror_lead, fastest_mirrors,
+    get_mirror_score_table, select_mirrors, Confidence, MirrorLatency, MirrorScore,
+    MirrorSelection, SpeedStats, SpeedtestOptions, SpeedtestOutcome, SpeedtestProgress,
+};
+pub use status::{read_status, status_file_string, SourceFormat, Status, STATUS_VERSION};
@@ -1,634 +1,925 @@
-use anyhow::{anyhow, Result};
-use futures::future;
-use indexmap::{indexmap, IndexMap};
-use indicatif::ProgressBar;
+use apt_gen_list::{
+    add_custom_mirror, apply_status, apply_undo, cached_score_table, check_freshness, check_status,
+    exclude_lagging_mirrors, fastest_mirror, fastest_mirror_confidence, fastest_mirror_lead,
+    find_history_entry, find_undo_entry, fl, get_mirror_score_table, get_mirror_url,
+    import_sources, is_root, list_mirrors, read_history, read_speedtest_cache, read_status,
+    record_speedtest, recover_transaction, remove_custom_mirror, select_mirrors,
+    status_file_string, write_file, ApplyStep, AvailableMirror, Context, DistroProfile, Error,
+    ExclusionReason, FileChange, MirrorLatency, MirrorScore, MirrorSelection, Result, SourceFormat,
+    SpeedtestOptions, SpeedtestOutcome, SpeedtestProgress, Status,
+};
+use chrono::{Local, TimeZone};
+use clap::ArgMatches;
+use indexmap::IndexMap;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use lazy_static::lazy_static;
-use log::warn;
-use os_release::OsRelease;
+use log::{Level, LevelFilter, Log, Metadata, Record};
 use owo_colors::OwoColorize;
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use serde::Serialize;
 use std::{
     collections::HashMap,
-    fs,
-    io::Write,
-    path::{Path, PathBuf},
-    process::Command,
-    time::{Duration, Instant},
+    env,
+    io::{self, Write},
+    process, thread,
 };
-use tokio::runtime::Builder;
 use url::Url;
 
 mod cli;
-mod i18n;
-
-use i18n::I18N_LOADER;
 
 lazy_static! {
-    static ref REPO_DATA_DIRECTORY: PathBuf = get_repo_data_path();
-    static ref REPO_MIRROR_FILE: PathBuf = REPO_DATA_DIRECTORY.join("mirrors.yml");
-    static ref REPO_COMPONENT_FILE: PathBuf = REPO_DATA_DIRECTORY.join("comps.yml");
-    static ref REPO_BRANCH_FILE: PathBuf = REPO_DATA_DIRECTORY.join("branches.yml");
+    /// Arguments of this invocation, recorded in the history
+    static ref COMMAND_LINE: String = env::args().skip(1).collect::<Vec<_>>().join(" ");
 }
 
-const STATUS_FILE: &str = "/var/lib/apt/gen/status.json";
-const APT_SOURCE_FILE: &str = "/etc/apt/sources.list";
-const CUSTOM_MIRROR_FILE: &str = "/etc/apt-gen-list/custom_mirror.yml";
-const SPEEDTEST_FILE_CHECKSUM: &str = "98900564fb4d9c7d3b63f44686c5b8a120af94a51fc6ca595e1406d5d8cc0416";
-const DOWNLOAD_PATH: &str = "misc/u-boot-sunxi-with-spl.bin";
-const SPEEDTEST_FILE_SIZE_KIB: f32 = 389.106_45;
-
-#[derive(Deserialize, Serialize)]
-struct Status {
-    branch: String,
-    component: Vec<String>,
-    mirror: IndexMap<String, String>,
+/// Machine-readable formats selected with `--output`
+#[derive(Clone, Copy)]
+enum OutputFormat {
+    Json,
+    Yaml,
 }
 
-#[cfg(feature = "aosc")]
-#[derive(Deserialize)]
-struct OldStatus {
-    branch: String,
-    component: Vec<String>,
-    mirror: Vec<String>,
-}
+impl OutputFormat {
+    /// `None` stands for human-readable text
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(OutputFormat::Json),
+            "yaml" => Some(OutputFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    fn print<T: Serialize>(&self, value: &T) -> Result<()> {
+        match self {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+            OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        }
 
-#[derive(Deserialize, Serialize)]
-struct BranchInfo {
-    desc: String,
-    suites: Vec<String>,
+        Ok(())
+    }
 }
 
-#[derive(Deserialize, Serialize)]
-struct MirrorInfo {
-    desc: String,
-    url: String,
+/// A mirror as printed by `list-mirrors`
+#[derive(Serialize)]
+struct ListedMirror {
+    #[serde(flatten)]
+    mirror: AvailableMirror,
+    enabled: bool,
+    /// Median speed measured by the latest speed test, with `--speed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes_per_second: Option<f64>,
+    /// When the speed was measured, in seconds since the Unix epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speedtest_time: Option<i64>,
 }
 
-type BranchesData = HashMap<String, BranchInfo>;
-type MirrorsData = IndexMap<String, MirrorInfo>;
-type ComponentData = HashMap<String, String>;
-type CustomMirrorData = HashMap<String, String>;
+/// Prints the warnings of the library to stderr, those of dependencies are not
+/// meant for users
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn && metadata.target().starts_with("apt_gen_list")
+    }
 
-#[cfg(feature = "aosc")]
-impl Default for Status {
-    fn default() -> Self {
-        Status {
-            branch: "stable".to_string(),
-            component: vec!["main".to_string()],
-            mirror: indexmap! {"origin".to_string() => "https://repo.aosc.io".to_string()},
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Error => eprintln!("Error: {}", record.args()),
+            Level::Warn => eprintln!("Warning: {}", record.args()),
+            _ => (),
         }
     }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+fn main() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Warn);
+    }
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        process::exit(e.exit_code());
+    }
 }
 
-fn main() -> Result<()> {
+fn run() -> Result<()> {
     let app = cli::build_cli().get_matches();
-    let mut status = read_status()?;
+    let ctx = Context::new(
+        app.value_of("root").unwrap_or("/"),
+        app.is_present("dry-run"),
+        !app.is_present("no-refresh"),
+        app.value_of("profile").and_then(DistroProfile::from_name),
+    );
+    // Taken before anything is read, it is held until the run ends
+    if !ctx.dry_run() && is_root() {
+        if !ctx.try_lock()? {
+            eprintln!("{}", fl!("waiting-for-lock"));
+        }
+        if recover_transaction(&ctx)? {
+            println!("{}", fl!("recovered-transaction"));
+        }
+    }
+    let output = OutputFormat::from_name(app.value_of("output").unwrap_or("text"));
+    match app.subcommand() {
+        // The status is what gets imported, it must not be created from the defaults first
+        Some(("import", args)) => return import(&ctx, output, args.is_present("apply")),
+        // Nothing was generated yet, e.g. when run from a dpkg trigger on a new system
+        Some(("regenerate", _)) if !ctx.status_file().is_file() => {
+            println!("{}", fl!("regenerate-no-status"));
+            return Ok(());
+        }
+        _ => (),
+    }
+    let mut status = read_status(&ctx)?;
 
     match app.subcommand() {
         Some(("status", _)) => {
-            let mirror_list = status
-                .mirror
-                .into_iter()
-                .map(|(mirror_name, mirror_url)| format!("{} ({})", mirror_name, mirror_url))
-                .collect::<Vec<String>>();
-            println!("{}", fl!("branch", branch = status.branch));
-            println!("{}", fl!("component", comp = status.component.join(", ")));
-            println!("{}", fl!("mirror", mirror = mirror_list.join(", ")));
+            if let Some(output) = output {
+                return output.print(&status);
+            }
+            print_status(&status);
         }
         Some(("set-mirror", args)) => {
-            set_mirror(args.value_of("MIRROR").unwrap(), &mut status)?;
+            set_mirror(&ctx, args.value_of("MIRROR").unwrap(), &mut status)?;
         }
         Some(("add-mirror", args)) => {
-            add_mirror(args.values_of("MIRROR").unwrap().collect(), &mut status)?;
+            add_mirror(
+                &ctx,
+                &args.values_of("MIRROR").unwrap().collect::<Vec<_>>(),
+                &mut status,
+            )?;
         }
         Some(("remove-mirror", args)) => {
-            remove_mirror(args, &mut status)?;
+            let entry: Vec<&str> = args.values_of("MIRROR").unwrap().collect();
+            status.remove_mirrors(&entry)?;
+            println!("{}", fl!("remove-mirror", mirror = entry.join(", ")));
+            apply(&ctx, &status)?;
         }
         Some(("add-component", args)) => {
-            add_component(args, &mut status)?;
+            let entries: Vec<&str> = args.values_of("COMPONENT").unwrap().collect();
+            status.add_components(&ctx, &entries)?;
+            println!("{}", fl!("enable-comp", comp = entries.join(", ")));
+            apply(&ctx, &status)?;
         }
         Some(("remove-component", args)) => {
-            remove_component(args.values_of("COMPONENT").unwrap().collect(), status)?;
+            let entry: Vec<&str> = args.values_of("COMPONENT").unwrap().collect();
+            status.remove_components(&entry)?;
+            println!("{}", fl!("disable-comp", comp = entry.join(", ")));
+            apply(&ctx, &status)?;
         }
         Some(("set-branch", args)) => {
             let new_branch = args.value_of("BRANCH").unwrap();
-            if read_distro_file::<BranchesData, _>(&*REPO_BRANCH_FILE)?
-                .get(new_branch)
-                .is_some()
-            {
-                status.branch = new_branch.to_string();
-            } else {
-                return Err(anyhow!(fl!("branch-not-found")));
-            }
+            status.set_branch(&ctx, new_branch)?;
             println!("{}", fl!("set-branch", branch = new_branch));
-            apply_status(&status)?;
+            apply(&ctx, &status)?;
+        }
+        Some(("set-format", args)) => {
+            let new_format = args.value_of("FORMAT").unwrap();
+            status.source_format = SourceFormat::from_name(new_format)
+                .ok_or_else(|| Error::FormatNotFound(new_format.to_string()))?;
+            if let Some(keyring) = args.value_of("signed-by") {
+                status.signed_by = Some(keyring.to_string()).filter(|keyring| !keyring.is_empty());
+            }
+            println!("{}", fl!("set-format", format = new_format));
+            apply(&ctx, &status)?;
         }
         Some(("speedtest", args)) => {
-            let mirrors_score_table = get_mirror_score_table(args.is_present("parallel"))?;
-            println!(" {:<20}Speed", "Mirror");
-            println!(" {:<20}---", "---");
-            for (mirror_name, score) in mirrors_score_table {
-                println!(" {:<20}{}", mirror_name, score);
+            let mut options = speedtest_options(&ctx, args, &status)?;
+            if let Some(mirrors) = args.values_of("MIRROR") {
+                options.mirrors = Some(named_mirrors(&ctx, mirrors)?);
+            }
+            let mirrors_score_table = run_speedtest(&ctx, &options)?;
+            if let Some(output) = output {
+                output.print(&mirrors_score_table)?;
+            } else {
+                // URLs given on the command line are longer than mirror names
+                let width = mirrors_score_table
+                    .iter()
+                    .map(|score| score.name.len() + 1)
+                    .fold(20, usize::max);
+                if options.rounds > 1 {
+                    println!(
+                        " {:<width$}{:<10}{:<14}{:<14}{:<14}Stddev",
+                        "Mirror",
+                        "Latency",
+                        "Median",
+                        "Min",
+                        "Max",
+                        width = width
+                    );
+                    println!(
+                        " {:<width$}{:<10}{:<14}{:<14}{:<14}---",
+                        "---",
+                        "---",
+                        "---",
+                        "---",
+                        "---",
+                        width = width
+                    );
+                    for score in &mirrors_score_table {
+                        let latency = format_latency(score.latency);
+                        match score.stats {
+                            Some(stats) => println!(
+                                " {:<width$}{:<10}{:<14}{:<14}{:<14}{}",
+                                score.name,
+                                latency,
+                                format_speed(stats.median),
+                                format_speed(stats.min),
+                                format_speed(stats.max),
+                                format_speed(stats.stddev),
+                                width = width
+                            ),
+                            None => println!(
+                                " {:<width$}{:<10}{}",
+                                score.name,
+                                latency,
+                                format_outcome(&score.outcome),
+                                width = width
+                            ),
+                        }
+                    }
+                } else {
+                    println!(" {:<width$}{:<10}Speed", "Mirror", "Latency", width = width);
+                    println!(" {:<width$}{:<10}---", "---", "---", width = width);
+                    for score in &mirrors_score_table {
+                        let latency = format_latency(score.latency);
+                        match score.bytes_per_second {
+                            Some(speed) => {
+                                println!(
+                                    " {:<width$}{:<10}{}",
+                                    score.name,
+                                    latency,
+                                    format_speed(speed),
+                                    width = width
+                                )
+                            }
+                            None => println!(
+                                " {:<width$}{:<10}{}",
+                                score.name,
+                                latency,
+                                format_outcome(&score.outcome),
+                                width = width
+                            ),
+                        }
+                    }
+                }
             }
+            fastest_mirror(&mirrors_score_table)?;
         }
-        Some(("set-fastest-mirror-as-default", _)) => {
-            set_fastest_mirror_as_default(status)?;
+        Some(("set-fastest-mirror-as-default", args)) => {
+            let max_age = args.value_of("max-age");
+            let options = speedtest_options(&ctx, args, &status)?;
+            let cached = match max_age.map(cli::parse_age) {
+                Some(Ok(max_age)) => cached_score_table(&ctx, &options, max_age)?
+                    .filter(|cached| fastest_mirror(cached).is_ok()),
+                _ => None,
+            };
+            let mut mirrors_score_table = match cached {
+                Some(cached) => {
+                    println!(
+                        "{}",
+                        fl!("speedtest-cached", age = max_age.unwrap_or_default())
+                    );
+                    cached
+                }
+                None => run_speedtest(&ctx, &options)?,
+            };
+            if let Some(Ok(max_lag)) = args.value_of("max-lag").map(cli::parse_age) {
+                println!("{}", fl!("check-freshness"));
+                let excluded =
+                    exclude_lagging_mirrors(&ctx, &status, &mut mirrors_score_table, max_lag)?;
+                for mirror in excluded {
+                    let name = mirror.name.as_str();
+                    let message = match mirror.reason {
+                        ExclusionReason::Unchecked => {
+                            fl!("exclude-unchecked-mirror", mirror = name)
+                        }
+                        ExclusionReason::Expired => fl!("exclude-expired-mirror", mirror = name),
+                        ExclusionReason::Lagging(lag) => fl!(
+                            "exclude-lagging-mirror",
+                            mirror = name,
+                            lag = format_lag(lag)
+                        ),
+                    };
+                    println!("{}", message);
+                }
+                if fastest_mirror(&mirrors_score_table).is_err() {
+                    return Err(Error::MirrorsLagging);
+                }
+            }
+            let count = args
+                .value_of("count")
+                .and_then(|s| cli::parse_count(s).ok());
+            let margin = args
+                .value_of("margin")
+                .and_then(|s| cli::parse_margin(s).ok());
+            let current = status.mirror.keys().next().cloned().unwrap_or_default();
+            let names =
+                match select_mirrors(&mirrors_score_table, count.unwrap_or(1), &current, margin)? {
+                    MirrorSelection::Fastest(fastest) => {
+                        print_fastest_mirrors(fastest, &mirrors_score_table);
+                        fastest.iter().map(|score| score.name.as_str()).collect()
+                    }
+                    MirrorSelection::KeepCurrent { mirrors, lead } => {
+                        println!(
+                            "{}",
+                            fl!(
+                                "keep-mirror",
+                                mirror = current.as_str(),
+                                fastest = mirrors_score_table[0].name.as_str(),
+                                percent = format!("{:.1}", lead),
+                                margin = margin.unwrap_or_default().to_string()
+                            )
+                        );
+                        if mirrors.len() == 1 {
+                            return Ok(());
+                        }
+                        mirrors
+                    }
+                    MirrorSelection::Unchanged => {
+                        println!("{}", fl!("keep-fastest-mirror", mirror = current.as_str()));
+                        return Ok(());
+                    }
+                };
+            status.set_mirrors(&ctx, &names)?;
+            println!("{}", fl!("set-mirror", mirror = names.join(", ")));
+            apply(&ctx, &status)?;
         }
         Some(("add-custom-mirror", args)) => {
             let custom_mirror_name = args.value_of("MIRROR_NAME").unwrap();
             let custom_mirror_url = args.value_of("MIRROR_URL").unwrap();
-            add_custom_mirror(custom_mirror_name, custom_mirror_url)?;
-            if args.is_present("also-set-mirror") {
-                set_mirror(custom_mirror_name, &mut status)?;
-            } else if args.is_present("also-add-mirror") {
-                add_mirror(vec![custom_mirror_name], &mut status)?;
+            if ctx.profile()?.custom_mirror_probe().is_some() {
+                println!("{}", fl!("trying-get-mirror"));
+            }
+            let change = add_custom_mirror(&ctx, custom_mirror_name, custom_mirror_url)?;
+            println!(
+                "{}",
+                fl!(
+                    "add-custom-mirror",
+                    mirror = custom_mirror_name,
+                    path = change.path.display().to_string()
+                )
+            );
+            print_changes(&ctx, &[change]);
+            if args.is_present("also-set-mirror") || args.is_present("also-add-mirror") {
+                // Nothing was written in dry-run mode, the new URL stands in for the file
+                let mirror_url = match get_mirror_url(&ctx, custom_mirror_name) {
+                    Err(Error::MirrorNotFound(_)) if ctx.dry_run() => {
+                        Url::parse(custom_mirror_url)?.to_string()
+                    }
+                    result => result?,
+                };
+                if args.is_present("also-set-mirror") {
+                    status.mirror.clear();
+                    status
+                        .mirror
+                        .insert(custom_mirror_name.to_string(), mirror_url);
+                    println!("{}", fl!("set-mirror", mirror = custom_mirror_name));
+                } else {
+                    println!("{}", fl!("add-mirror", mirror = custom_mirror_name));
+                    status
+                        .mirror
+                        .entry(custom_mirror_name.to_string())
+                        .or_insert(mirror_url);
+                }
+                apply(&ctx, &status)?;
             }
         }
         Some(("remove-custom-mirror", args)) => {
             let custom_mirror_args = args.values_of("MIRROR").unwrap();
             for entry in custom_mirror_args {
-                remove_custom_mirror(entry)?;
+                let change = remove_custom_mirror(&ctx, entry)?;
+                println!(
+                    "{}",
+                    fl!(
+                        "remove-custom-mirror",
+                        mirror = entry,
+                        path = change.path.display().to_string()
+                    )
+                );
+                print_changes(&ctx, &[change]);
             }
         }
         Some(("reset-mirror", _)) => {
-            #[cfg(feature = "aosc")]
-            {
-                status = Status::default();
-                apply_status(&status)?;
+            let profile = ctx.profile()?;
+            let default_status = profile
+                .default_status()
+                .ok_or_else(|| Error::NoDefaultStatus(profile.name().to_string()))?;
+            status = Status {
+                source_format: status.source_format,
+                signed_by: status.signed_by.take(),
+                ..default_status
+            };
+            apply(&ctx, &status)?;
+        }
+        Some(("regenerate", args)) => {
+            let old_status = status.clone();
+            let drop_missing = args.is_present("drop-missing");
+            for drift in status.refresh_mirror_urls(&ctx, drop_missing)? {
+                println!(
+                    "{}",
+                    fl!(
+                        "check-mirror",
+                        mirror = drift.name.as_str(),
+                        url = drift.url.as_str(),
+                        reason = drift.reason.to_string()
+                    )
+                );
             }
-            #[cfg(not(feature = "aosc"))]
-            {
-                unreachable!();
+            if status == old_status && check_status(&ctx, &status)?.sources.is_empty() {
+                println!("{}", fl!("regenerate-up-to-date"));
+            } else {
+                println!("{}", fl!("regenerate"));
+                apply(&ctx, &status)?;
             }
         }
-        Some(("list-mirrors", _)) => {
-            get_available_mirror(&status)?;
+        Some(("check", _)) => {
+            let result = check_status(&ctx, &status)?;
+            if let Some(output) = output {
+                output.print(&result)?;
+            } else {
+                for change in &result.sources {
+                    println!(
+                        "{}",
+                        fl!("check-sources", path = change.path.display().to_string())
+                    );
+                    print!("{}", change.unified_diff());
+                }
+                for mirror in &result.mirrors {
+                    println!(
+                        "{}",
+                        fl!(
+                            "check-mirror",
+                            mirror = mirror.name.as_str(),
+                            url = mirror.url.as_str(),
+                            reason = mirror.reason.to_string()
+                        )
+                    );
+                }
+                if result.is_clean() {
+                    println!("{}", fl!("check-clean"));
+                }
+            }
+            if !result.is_clean() {
+                return Err(Error::DriftDetected);
+            }
         }
-        _ => {
-            unreachable!()
+        Some(("freshness", args)) => {
+            let mirrors = if args.is_present("enabled") {
+                status.mirror.clone()
+            } else {
+                list_mirrors(&ctx)?
+                    .into_iter()
+                    .map(|mirror| (mirror.name, mirror.url))
+                    .collect()
+            };
+            let freshness = check_freshness(&ctx, &status, &mirrors)?;
+            if let Some(output) = output {
+                return output.print(&freshness);
+            }
+            println!(" {:<20}{:<14}{:<19}Status", "Mirror", "Lag", "Date");
+            println!(" {:<20}{:<14}{:<19}---", "---", "---", "---");
+            for mirror in &freshness {
+                let lag = mirror.lag.map_or_else(|| "-".to_string(), format_lag);
+                let date = mirror
+                    .suites
+                    .iter()
+                    .filter_map(|suite| suite.date)
+                    .max()
+                    .and_then(|date| Local.timestamp_opt(date, 0).single())
+                    .map_or_else(
+                        || "-".to_string(),
+                        |date| date.format("%Y-%m-%d %H:%M").to_string(),
+                    );
+                let state = match mirror.suites.iter().find_map(|suite| suite.error.as_ref()) {
+                    Some(error) => fl!("speedtest-failed", reason = error.as_str())
+                        .red()
+                        .to_string(),
+                    None if mirror.expired => fl!("freshness-expired").red().to_string(),
+                    None => String::new(),
+                };
+                println!(" {:<20}{:<14}{:<19}{}", mirror.name, lag, date, state);
+            }
         }
-    }
-
-    Ok(())
-}
-
-fn get_repo_data_path() -> PathBuf {
-    let not_local_directory_path = PathBuf::from("/usr/share/distro-repository-data/");
-    if not_local_directory_path.is_dir() {
-        not_local_directory_path
-    } else {
-        PathBuf::from("/usr/local/share/distro-repository-data/")
-    }
-}
-
-fn set_fastest_mirror_as_default(mut status: Status) -> Result<()> {
-    let mirrors_score_table = get_mirror_score_table(false)?;
-    println!(
-        "{}",
-        fl!(
-            "set-fastest-mirror",
-            mirror = mirrors_score_table[0].0.clone(),
-            speed = mirrors_score_table[0].1.clone()
-        )
-    );
-    set_mirror(mirrors_score_table[0].0.as_str(), &mut status)?;
-
-    Ok(())
-}
-
-fn get_mirror_score_table(is_parallel: bool) -> Result<Vec<(String, String)>> {
-    let mirrors_indexmap = read_distro_file::<MirrorsData, _>(&*REPO_MIRROR_FILE)?;
-    let bar = ProgressBar::new_spinner();
-    let mut mirrors_score_table = if is_parallel {
-        bar.set_message(fl!("test-mirrors"));
-        let runtime = Builder::new_multi_thread()
-            .enable_all()
-            .worker_threads(2)
-            .build()
-            .unwrap();
-        let client = reqwest::Client::new();
-        runtime.block_on(async move {
-            let task = mirrors_indexmap
-                .keys()
+        Some(("history", _)) => {
+            let history = read_history(&ctx.history_file())?;
+            println!(" {:<6}{:<21}Command", "ID", "Time");
+            println!(" {:<6}{:<21}---", "---", "---");
+            for entry in history {
+                println!(
+                    " {:<6}{:<21}{}",
+                    entry.id,
+                    entry.time_string(),
+                    entry.command_string()
+                );
+            }
+        }
+        Some(("undo", _)) => {
+            let entry = find_undo_entry(&ctx.history_file())?;
+            println!("{}", fl!("undo"));
+            let changes = apply_undo(&ctx, &entry, &COMMAND_LINE, print_step)?;
+            print_changes(&ctx, &changes);
+        }
+        Some(("revert", args)) => {
+            let id = args.value_of_t::<u64>("ID").unwrap_or_else(|e| e.exit());
+            let status = find_history_entry(&ctx.history_file(), id)?;
+            println!("{}", fl!("revert", id = id));
+            apply(&ctx, &status)?;
+        }
+        Some(("list-mirrors", args)) => {
+            let show_speed = args.is_present("speed");
+            let cache = if show_speed {
+                read_speedtest_cache(&ctx)
+            } else {
+                Default::default()
+            };
+            let mut mirrors = list_mirrors(&ctx)?
                 .into_iter()
-                .map(|x| get_mirror_speed_score_parallel(x, &client))
+                .map(|mirror| {
+                    let cached = cache.get(&mirror.name);
+                    ListedMirror {
+                        enabled: status.mirror.contains_key(&mirror.name),
+                        bytes_per_second: cached.and_then(|cached| cached.score.bytes_per_second),
+                        speedtest_time: cached.map(|cached| cached.time),
+                        mirror,
+                    }
+                })
                 .collect::<Vec<_>>();
-            bar.enable_steady_tick(50);
-            let results = future::join_all(task).await;
-            let mut result = Vec::new();
-            for (index, mirror_name) in mirrors_indexmap.keys().enumerate() {
-                if let Ok(time) = results[index] {
-                    result.push((mirror_name.to_owned(), SPEEDTEST_FILE_SIZE_KIB / time));
-                }
+            if show_speed {
+                // Fastest first, mirrors without a speed keep their order at the end
+                mirrors.sort_by(|a, b| {
+                    let a = a.bytes_per_second.unwrap_or(f64::NEG_INFINITY);
+                    let b = b.bytes_per_second.unwrap_or(f64::NEG_INFINITY);
+                    b.total_cmp(&a)
+                });
             }
-
-            result
-        })
-    } else {
-        let mut result = Vec::new();
-        for (index, mirror_name) in mirrors_indexmap.keys().enumerate() {
-            bar.set_message(fl!(
-                "test-mirrors-sync",
-                count = index,
-                all = mirrors_indexmap.len()
-            ));
-            bar.enable_steady_tick(50);
-            if let Ok(time) = get_mirror_speed_score(mirror_name) {
-                result.push((mirror_name.to_owned(), SPEEDTEST_FILE_SIZE_KIB / time));
+            if let Some(output) = output {
+                return output.print(&mirrors);
+            }
+            println!("  {}\n", fl!("mirror-list-explain"));
+            for ListedMirror {
+                mirror,
+                enabled,
+                bytes_per_second,
+                ..
+            } in mirrors
+            {
+                let mirror_info = match mirror.desc {
+                    Some(desc) => desc,
+                    None => format!("{} {}", fl!("custom"), mirror.url),
+                };
+                let s = if show_speed {
+                    let speed = bytes_per_second.map_or_else(|| "-".to_string(), format_speed);
+                    format!("{:<10}{:<14}{}", mirror.name, speed, mirror_info)
+                } else {
+                    format!("{:<10}{}", mirror.name, mirror_info)
+                };
+                if enabled {
+                    println!("* {}", s.cyan().bold());
+                    continue;
+                }
+                println!("  {}", s);
             }
         }
-
-        result
-    };
-    mirrors_score_table.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap());
-    if mirrors_score_table.is_empty() {
-        return Err(anyhow!(fl!("mirror-test-failed")));
-    }
-    let mut result = Vec::new();
-    for (mirror_name, mut score) in mirrors_score_table {
-        let mut unit = "KiB/s";
-        if score > 1000.0 {
-            score /= 1024.0;
-            unit = "MiB/s";
-        }
-        result.push((mirror_name.to_owned(), format!("{:.2}{}", score, unit)));
-    }
-
-    Ok(result)
-}
-
-fn get_available_mirror(status: &Status) -> Result<()> {
-    let mut result_table = IndexMap::new();
-    let distro_mirror = read_distro_file::<MirrorsData, _>(&*REPO_MIRROR_FILE)?;
-    for (mirror_name, mirror_info) in distro_mirror {
-        result_table.insert(mirror_name, mirror_info.desc);
-    }
-    if let Ok(custom_mirror) = read_distro_file::<CustomMirrorData, _>(CUSTOM_MIRROR_FILE) {
-        for (mirror_name, mirror_url) in custom_mirror {
-            result_table.insert(mirror_name, format!("{} {}", fl!("custom"), mirror_url));
+        _ => {
+            unreachable!()
         }
     }
-    result_table.sort_keys();
-    println!("  {}\n", fl!("mirror-list-explain"));
-    for (mirror_name, mirror_info) in &result_table {
-        let s = format!("{:<10}{}", mirror_name, mirror_info);
-        if status.mirror.get(mirror_name).is_some() {
-            println!("* {}", s.cyan().bold());
-            continue;
-        }
-        println!("  {}", s);
-    }
 
     Ok(())
 }
 
-fn set_mirror(new_mirror: &str, status: &mut Status) -> Result<()> {
-    status.mirror = indexmap! {new_mirror.to_string() => get_mirror_url(new_mirror)?};
-    println!("{}", fl!("set-mirror", mirror = new_mirror));
-    apply_status(&*status)?;
-
-    Ok(())
+fn print_status(status: &Status) {
+    let mirror_list = status
+        .mirror
+        .iter()
+        .map(|(mirror_name, mirror_url)| format!("{} ({})", mirror_name, mirror_url))
+        .collect::<Vec<String>>();
+    println!("{}", fl!("branch", branch = status.branch.as_str()));
+    println!("{}", fl!("component", comp = status.component.join(", ")));
+    println!("{}", fl!("mirror", mirror = mirror_list.join(", ")));
+    println!("{}", fl!("format", format = status.source_format.name()));
 }
 
-fn remove_mirror(args: &clap::ArgMatches, status: &mut Status) -> Result<()> {
-    if status.mirror.len() == 1 {
-        return Err(anyhow!(fl!("no-delete-only-mirror")));
-    }
-    let entry: Vec<&str> = args.values_of("MIRROR").unwrap().collect();
-    for i in &entry {
-        if status.mirror.get(i.to_owned()).is_some() {
-            status.mirror.remove(i.to_owned());
-        } else {
-            return Err(anyhow!(
+/// Write the status imported from the APT sources, and regenerate them if `also_apply`
+fn import(ctx: &Context, output: Option<OutputFormat>, also_apply: bool) -> Result<()> {
+    let result = import_sources(ctx)?;
+    if let Some(output) = output {
+        output.print(&result)?;
+    } else {
+        println!("{}", fl!("import"));
+        for entry in &result.unmapped {
+            println!(
                 "{}",
-                fl!("mirror-not-found", mirror = i.to_string())
-            ));
+                fl!(
+                    "import-unmapped",
+                    path = entry.path.display().to_string(),
+                    line = entry.line,
+                    reason = entry.reason.to_string()
+                )
+            );
         }
+        print_status(&result.status);
     }
-    println!("{}", fl!("remove-mirror", mirror = entry.join(", ")));
-    apply_status(&*status)?;
+    // Only the result goes to stdout in a machine-readable format
+    let mut out: Box<dyn Write> = match output {
+        Some(_) => Box::new(io::stderr()),
+        None => Box::new(io::stdout()),
+    };
+    let changes = if also_apply {
+        apply_status(ctx, &result.status, &COMMAND_LINE, |step| {
+            writeln!(out, "{}", step_message(step)).ok();
+        })?
+    } else {
+        writeln!(out, "{}", fl!("write-status"))?;
+        vec![write_file(
+            ctx,
+            &ctx.status_file(),
+            &status_file_string(&result.status)?,
+        )?]
+    };
+    write_changes(ctx, &changes, &mut out)?;
 
     Ok(())
 }
 
-fn add_mirror(entry: Vec<&str>, status: &mut Status) -> Result<()> {
-    println!("{}", fl!("add-mirror", mirror = entry.join(", ")));
-    for i in entry {
-        let mirror_url = get_mirror_url(i)?;
-        if status.mirror.get(i).is_some() {
-            warn!("{}", fl!("mirror-already-enabled", mirror = i.to_string()));
-        } else {
-            status.mirror.insert(i.to_string(), mirror_url);
-        }
-    }
-    apply_status(&*status)?;
+fn set_mirror(ctx: &Context, new_mirror: &str, status: &mut Status) -> Result<()> {
+    status.set_mirror(ctx, new_mirror)?;
+    println!("{}", fl!("set-mirror", mirror = new_mirror));
 
-    Ok(())
+    apply(ctx, status)
 }
 
-fn add_custom_mirror(mirror_name: &str, mirror_url: &str) -> Result<()> {
-    if read_distro_file::<MirrorsData, _>(&*REPO_MIRROR_FILE)?
-        .get(mirror_name)
-        .is_some()
-    {
-        return Err(anyhow!(fl!("custom-mirror-name-error")));
-    }
-    let url = Url::parse(mirror_url).map_err(|_| anyhow!(fl!("custom-mirror-not-url")))?;
-    #[cfg(feature = "aosc")]
-    {
-        for i in &["debs", "debs/", "debs-retro", "debs-retro/"] {
-            if mirror_url.ends_with(i) {
-                return Err(anyhow!(fl!("debs-path-in-url")));
-            }
-        }
-        println!("{}", fl!("trying-get-mirror"));
-        let get_mirror = reqwest::blocking::Client::builder()
-            .timeout(Duration::from_secs(10))
-            .build()?
-            .get(url.join("pool/stable/InRelease")?)
-            .send();
-        if get_mirror.is_err() {
-            return Err(anyhow!(fl!("download-mirror-metadata-failed")));
-        }
-    }
-    #[cfg(not(feature = "aosc"))]
-    {
-        if url.scheme().is_empty() {
-            return Err(anyhow!(fl!("custom-mirror-not-url")));
-        }
-    }
-    println!(
-        "{}",
-        fl!(
-            "add-custom-mirror",
-            mirror = mirror_name,
-            path = CUSTOM_MIRROR_FILE
-        )
-    );
-    let mut custom_mirror_data = match read_distro_file::<CustomMirrorData, _>(CUSTOM_MIRROR_FILE) {
-        Ok(v) => v,
-        Err(_) => {
-            fs::create_dir_all("/etc/apt-gen-list")?;
-            fs::File::create(CUSTOM_MIRROR_FILE)?;
-            let mut result = HashMap::new();
-            result.insert(mirror_name.to_string(), url.to_string());
-            fs::write(CUSTOM_MIRROR_FILE, serde_yaml::to_string(&result)?)?;
+fn add_mirror(ctx: &Context, entry: &[&str], status: &mut Status) -> Result<()> {
+    println!("{}", fl!("add-mirror", mirror = entry.join(", ")));
+    status.add_mirrors(ctx, entry)?;
 
-            result
-        }
-    };
-    if custom_mirror_data.get(mirror_name).is_none() {
-        custom_mirror_data.insert(mirror_name.to_string(), url.to_string());
+    apply(ctx, status)
+}
+
+/// Report the mirrors picked by a speed test and how clearly the fastest one won
+fn print_fastest_mirrors(fastest: &[MirrorScore], mirrors_score_table: &[MirrorScore]) {
+    if let [fastest] = fastest {
+        println!(
+            "{}",
+            fl!(
+                "set-fastest-mirror",
+                mirror = fastest.name.as_str(),
+                speed = format_speed(fastest.bytes_per_second.unwrap_or_default())
+            )
+        );
     } else {
-        warn!(
+        let mirrors = fastest
+            .iter()
+            .map(|score| {
+                format!(
+                    "{} ({})",
+                    score.name,
+                    format_speed(score.bytes_per_second.unwrap_or_default())
+                )
+            })
+            .collect::<Vec<_>>();
+        println!(
             "{}",
-            fl!("custom-mirror-already-exist", mirror = mirror_name)
+            fl!("set-fastest-mirrors", mirrors = mirrors.join(", "))
+        );
+    }
+    if let Some(confidence) = fastest_mirror_confidence(mirrors_score_table) {
+        let runner_up = &mirrors_score_table[1];
+        let ahead = fastest_mirror_lead(mirrors_score_table, &runner_up.name).unwrap_or_default();
+        println!(
+            "{}",
+            fl!(
+                "fastest-mirror-confidence",
+                confidence = confidence.to_string(),
+                percent = format!("{:.1}", ahead),
+                mirror = runner_up.name.as_str()
+            )
         );
     }
-    fs::write(
-        CUSTOM_MIRROR_FILE,
-        serde_yaml::to_string(&custom_mirror_data)?,
-    )?;
-
-    Ok(())
 }
 
-fn remove_custom_mirror(mirror_name: &str) -> Result<()> {
-    let mut custom_mirror = read_distro_file::<CustomMirrorData, _>(CUSTOM_MIRROR_FILE)?;
-    if custom_mirror.get(mirror_name).is_none() {
-        return Err(anyhow!(fl!(
-            "custom-mirror-not-found",
-            mirror = mirror_name
-        )));
-    } else {
-        custom_mirror.remove(mirror_name);
-    }
-    println!(
-        "{}",
-        fl!(
-            "remove-custom-mirror",
-            mirror = mirror_name,
-            path = CUSTOM_MIRROR_FILE
-        )
-    );
-    fs::write(CUSTOM_MIRROR_FILE, serde_yaml::to_string(&custom_mirror)?)?;
+/// Apply `status` while reporting each step, or show the diff in dry-run mode
+fn apply(ctx: &Context, status: &Status) -> Result<()> {
+    let changes = apply_status(ctx, status, &COMMAND_LINE, print_step)?;
+    print_changes(ctx, &changes);
 
     Ok(())
 }
 
-fn remove_component(entry: Vec<&str>, mut status: Status) -> Result<()> {
-    if !entry.contains(&"main") {
-        for i in &entry {
-            if let Some(index) = status.component.iter().position(|v| v == i) {
-                status.component.remove(index);
-            } else {
-                warn!("{}", fl!("comp-not-enabled", comp = i.to_string()));
-            }
+fn print_step(step: ApplyStep) {
+    println!("{}", step_message(step));
+}
+
+fn step_message(step: ApplyStep) -> String {
+    match step {
+        ApplyStep::WriteStatus => fl!("write-status"),
+        ApplyStep::WriteSources(path) => {
+            fl!("write-sources", path = path.display().to_string())
         }
-    } else {
-        return Err(anyhow!(fl!("no-delete-only-comp")));
+        ApplyStep::RemoveSources(path) => {
+            fl!("remove-sources", path = path.display().to_string())
+        }
+        ApplyStep::RunCommand(command) => fl!("run-command", command = command),
+        ApplyStep::SkipCommand(command) => fl!("skip-command", command = command),
+        ApplyStep::DuplicateEntry(entry) => fl!(
+            "duplicate-entry",
+            path = entry.path.display().to_string(),
+            line = entry.line,
+            mirror = entry.mirror.as_str()
+        ),
+        ApplyStep::RunHook(hook) => fl!("run-hook", hook = hook.display().to_string()),
+        ApplyStep::Rollback => fl!("rollback"),
     }
-    println!("{}", fl!("disable-comp", comp = entry.join(", ")));
-    apply_status(&status)?;
+}
 
-    Ok(())
+fn print_changes(ctx: &Context, changes: &[FileChange]) {
+    write_changes(ctx, changes, &mut io::stdout()).ok();
 }
 
-fn add_component(args: &clap::ArgMatches, status: &mut Status) -> Result<()> {
-    let entries: Vec<&str> = args.values_of("COMPONENT").unwrap().collect();
-    for entry in entries.iter() {
-        let entry_str = entry.to_string();
-        if status.component.contains(&entry_str) {
-            warn!("{}", fl!("comp-already-enabled", comp = entry_str.clone()));
-        } else if read_distro_file::<ComponentData, _>(&*REPO_COMPONENT_FILE)?
-            .get(&entry_str)
-            .is_some()
-        {
-            status.component.push(entry_str);
-        } else {
-            return Err(anyhow!(fl!("comp-not-found", comp = entry_str)));
+/// Write the diff of each change in dry-run mode
+fn write_changes<W: Write>(ctx: &Context, changes: &[FileChange], out: &mut W) -> Result<()> {
+    if ctx.dry_run() {
+        for change in changes {
+            write!(out, "{}", change.unified_diff())?;
         }
     }
-    println!("{}", fl!("enable-comp", comp = entries.join(", ")));
-    apply_status(status)?;
 
     Ok(())
 }
 
-fn read_status() -> Result<Status> {
-    if !Path::new(STATUS_FILE).is_file() && !is_root() {
-        panic!("{}", fl!("status-file-not-found", path = STATUS_FILE))
-    }
-    match fs::read(STATUS_FILE) {
-        Ok(file) => match serde_json::from_slice(&file) {
-            Ok(status) => Ok(status),
-            Err(_) => {
-                #[cfg(feature = "aosc")]
-                {
-                    if !is_root() {
-                        return Err(anyhow!("{}", fl!("status-file-read-error")));
+/// Run a speed test with a spinner while measuring latency, then a progress
+/// bar for each mirror
+fn run_speedtest(ctx: &Context, options: &SpeedtestOptions) -> Result<Vec<MirrorScore>> {
+    let spinner = ProgressBar::new_spinner();
+    spinner.enable_steady_tick(50);
+    spinner.set_message(fl!("test-mirrors-latency"));
+    let mut bars = HashMap::new();
+    let mut drawing = None;
+    let style = ProgressStyle::default_bar()
+        .template(
+            " {prefix:<20} [{bar:30}] {bytes:>10}/{total_bytes:<10} {bytes_per_sec:>12} {msg}",
+        )
+        .progress_chars("=> ");
+    let result = get_mirror_score_table(ctx, options, |progress| match progress {
+        SpeedtestProgress::Start(mirrors) => {
+            spinner.finish_and_clear();
+            let multi = MultiProgress::new();
+            // Mirrors waiting for their turn only show their name
+            let waiting = ProgressStyle::default_bar().template(" {prefix:<20} {msg}");
+            for mirror in mirrors {
+                let bar = ProgressBar::new(0)
+                    .with_style(waiting.clone())
+                    .with_prefix(mirror.to_string());
+                bars.insert(mirror.to_string(), multi.add(bar));
+            }
+            // Bars of a MultiProgress are only drawn while it is joined
+            drawing = Some(thread::spawn(move || multi.join_and_clear()));
+        }
+        SpeedtestProgress::Download {
+            mirror,
+            round,
+            bytes,
+            size,
+        } => {
+            if let Some(bar) = bars.get(mirror) {
+                if bytes == 0 {
+                    bar.set_style(style.clone());
+                    bar.reset();
+                    bar.set_length(size);
+                    if options.rounds > 1 {
+                        let rounds = options.rounds;
+                        bar.set_message(fl!("test-mirror-round", round = round, rounds = rounds));
                     }
-                    let status = trans_to_new_status_config(file).unwrap_or_default();
-                    fs::write(STATUS_FILE, serde_json::to_string(&status)?)?;
-
-                    Ok(status)
-                }
-                #[cfg(not(feature = "aosc"))]
-                {
-                    panic!("{}", fl!("status-file-read-error"));
                 }
+                bar.set_position(bytes);
             }
-        },
-        Err(_) => {
-            #[cfg(feature = "aosc")]
-            {
-                fs::create_dir_all("/var/lib/apt/gen")?;
-                let status = Status::default();
-                fs::write(STATUS_FILE, serde_json::to_string(&status)?)?;
-
-                Ok(status)
-            }
-            #[cfg(not(feature = "aosc"))]
-            {
-                panic!("{}", fl!("status-file-read-error"));
+        }
+        SpeedtestProgress::Done(mirror) => {
+            if let Some(bar) = bars.get(mirror) {
+                bar.finish_at_current_pos();
             }
         }
+    });
+    spinner.finish_and_clear();
+    for bar in bars.values() {
+        bar.finish_at_current_pos();
     }
-}
-
-fn is_root() -> bool {
-    nix::unistd::geteuid().is_root()
-}
-
-#[cfg(feature = "aosc")]
-fn trans_to_new_status_config(file: Vec<u8>) -> Result<Status> {
-    let status: OldStatus = serde_json::from_slice(&file)?;
-    let mut new_mirror: IndexMap<String, String> = IndexMap::new();
-    for mirror_name in &status.mirror {
-        new_mirror.insert(mirror_name.to_string(), get_mirror_url(mirror_name)?);
+    if let Some(drawing) = drawing {
+        drawing.join().ok();
     }
+    let mirrors_score_table = result?;
+    record_speedtest(ctx, &mirrors_score_table)?;
 
-    Ok(Status {
-        branch: status.branch,
-        mirror: new_mirror,
-        component: status.component,
-    })
+    Ok(mirrors_score_table)
 }
 
-fn read_distro_file<T: for<'de> Deserialize<'de>, P: AsRef<Path>>(file: P) -> Result<T> {
-    Ok(serde_yaml::from_slice(&fs::read(file)?)?)
-}
+/// Options of `speedtest` and `set-fastest-mirror-as-default`
+fn speedtest_options(
+    ctx: &Context,
+    args: &ArgMatches,
+    status: &Status,
+) -> Result<SpeedtestOptions> {
+    let optional = |name| {
+        args.is_present(name)
+            .then(|| args.value_of_t::<usize>(name).unwrap_or_else(|e| e.exit()))
+    };
+    let seconds = |name| {
+        args.value_of(name)
+            .and_then(|seconds| cli::parse_seconds(seconds).ok())
+    };
+    let concurrency = match optional("concurrency") {
+        Some(concurrency) => Some(concurrency),
+        None if args.is_present("parallel") => None,
+        None => Some(1),
+    };
 
-fn apply_status(status: &Status) -> Result<()> {
-    println!("{}", fl!("write-status"));
-    fs::write(
-        STATUS_FILE,
-        format!("{}\n", serde_json::to_string(&status)?),
-    )?;
-    #[cfg(all(feature = "aosc", not(feature = "retro")))]
-    {
-        println!("{}", fl!("run-atm-refresh"));
-        Command::new("atm")
-            .arg("refresh")
-            .spawn()?
-            .wait_with_output()?;
-    }
-    let source_list_str = gen_sources_list_string(status)?;
-    println!("{}", fl!("write-sources"));
-    fs::write(APT_SOURCE_FILE, source_list_str)?;
-    println!("{}", fl!("run-apt"));
-    Command::new("apt-get")
-        .arg("update")
-        .spawn()?
-        .wait_with_output()?;
+    let mirrors = if args.is_present("enabled") {
+        Some(status.mirror.clone())
+    } else if args.is_present("include-custom") {
+        Some(
+            list_mirrors(ctx)?
+                .into_iter()
+                .map(|mirror| (mirror.name, mirror.url))
+                .collect(),
+        )
+    } else {
+        None
+    };
 
-    Ok(())
+    Ok(SpeedtestOptions {
+        concurrency,
+        rounds: args.value_of_t("rounds").unwrap_or_else(|e| e.exit()),
+        top: optional("top"),
+        connect_timeout: seconds("connect-timeout").unwrap_or_default(),
+        read_timeout: seconds("read-timeout").unwrap_or_default(),
+        deadline: seconds("deadline"),
+        mirrors,
+    })
 }
 
-fn gen_sources_list_string(status: &Status) -> Result<String> {
-    let mut result = format!("{}\n", fl!("generated"));
-    let directory_name = get_directory_name();
-    for (_, mirror_url) in &status.mirror {
-        let debs_url = Url::parse(mirror_url)?.join(directory_name)?;
-        for branch in get_branch_suites(&status.branch)? {
-            result.push_str(&format!(
-                "deb {} {} {}\n",
-                debs_url.as_str(),
-                branch,
-                status.component.join(" ")
-            ));
-        }
+/// Mirrors named on the command line with their URL, a URL is tested as it is
+fn named_mirrors<'a, I: Iterator<Item = &'a str>>(
+    ctx: &Context,
+    mirrors: I,
+) -> Result<IndexMap<String, String>> {
+    let mut result = IndexMap::new();
+    for mirror in mirrors {
+        let mirror_url = if mirror.contains("://") {
+            Url::parse(mirror).map_err(|_| Error::CustomMirrorNotUrl(mirror.to_string()))?;
+            mirror.to_string()
+        } else {
+            get_mirror_url(ctx, mirror)?
+        };
+        result.insert(mirror.to_string(), mirror_url);
     }
 
     Ok(result)
 }
 
-async fn get_mirror_speed_score_parallel(mirror_name: &str, client: &Client) -> Result<f32> {
-    let download_url = Url::parse(&get_mirror_url(mirror_name)?)?.join(DOWNLOAD_PATH)?;
-    let timer = Instant::now();
-    let file = client
-        .get(download_url)
-        .timeout(Duration::from_secs(10))
-        .send()
-        .await?
-        .bytes()
-        .await?;
-    let mut hasher = Sha256::new();
-    hasher.write_all(&file)?;
-    if hex::encode(hasher.finalize()) == SPEEDTEST_FILE_CHECKSUM {
-        let result_time = timer.elapsed().as_secs_f32();
-        return Ok(result_time);
+/// A lag in seconds, rounded to its two largest units
+fn format_lag(lag: i64) -> String {
+    let (days, hours) = (lag / 86400, lag % 86400 / 3600);
+    let (minutes, seconds) = (lag % 3600 / 60, lag % 60);
+    match lag {
+        0 => fl!("freshness-up-to-date"),
+        _ if days > 0 => format!("{}d {}h", days, hours),
+        _ if hours > 0 => format!("{}h {}m", hours, minutes),
+        _ if minutes > 0 => format!("{}m {}s", minutes, seconds),
+        _ => format!("{}s", seconds),
     }
-
-    Err(anyhow!(fl!("mirror-error", mirror = mirror_name)))
 }
 
-fn get_mirror_speed_score(mirror_name: &str) -> Result<f32> {
-    let download_url = Url::parse(&get_mirror_url(mirror_name)?)?.join(DOWNLOAD_PATH)?;
-    let client = reqwest::blocking::Client::builder()
-        .timeout(Duration::from_secs(10))
-        .build()?;
-    let timer = Instant::now();
-    let file = client.get(download_url).send()?.bytes()?;
-    let mut hasher = Sha256::new();
-    hasher.write_all(&file)?;
-    let c = hex::encode(hasher.finalize());
-    if c == SPEEDTEST_FILE_CHECKSUM {
-        let result_time = timer.elapsed().as_secs_f32();
-        return Ok(result_time);
+/// Time to first byte of a HEAD request, in milliseconds
+fn format_latency(latency: Option<MirrorLatency>) -> String {
+    match latency {
+        Some(latency) => format!("{:.0}ms", latency.ttfb * 1000.0),
+        None => "-".to_string(),
     }
-
-    Err(anyhow!(fl!("mirror-error", mirror = mirror_name)))
 }
 
-fn get_mirror_url(mirror_name: &str) -> Result<String> {
-    if let Some(mirror_info) =
-        read_distro_file::<MirrorsData, _>(&*REPO_MIRROR_FILE)?.get(mirror_name)
-    {
-        return Ok(mirror_info.url.to_owned());
-    } else if let Some(mirror_url) =
-        read_distro_file::<CustomMirrorData, _>(CUSTOM_MIRROR_FILE)?.get(mirror_name)
-    {
-        return Ok(mirror_url.to_owned());
+/// Why a mirror has no speed in the speedtest table
+fn format_outcome(outcome: &SpeedtestOutcome) -> String {
+    match outcome {
+        SpeedtestOutcome::Skipped => outcome.to_string().dimmed().to_string(),
+        _ => fl!("speedtest-failed", reason = outcome.to_string())
+            .red()
+            .to_string(),
     }
-
-    Err(anyhow!(fl!("mirror-not-found", mirror = mirror_name)))
 }
 
-fn get_branch_suites(branch_name: &str) -> Result<Vec<String>> {
-    Ok(read_distro_file::<BranchesData, _>(&*REPO_BRANCH_FILE)?
-        .get(branch_name)
-        .ok_or_else(|| anyhow!(fl!("branch-data-error")))?
-        .suites
-        .to_owned())
-}
-
-fn get_directory_name() -> &'static str {
-    match OsRelease::new().unwrap().name.as_str() {
-        "AOSC OS" => "debs",
-        "AOSC OS/Retro" => "debs-retro",
-        _ => "",
+fn format_speed(bytes_per_second: f64) -> String {
+    let mut score = bytes_per_second / 1024.0;
+    let mut unit = "KiB/s";
+    if score > 1000.0 {
+        score /= 1024.0;
+        unit = "MiB/s";
     }
+
+    format!("{:.2}{}", score, unit)
 }
@@ -0,0 +1,116 @@
+use indexmap::indexmap;
+use os_release::OsRelease;
+
+use crate::{SourceFormat, SpeedtestProbe, Status};
+
+/// Distro-specific behavior of apt-gen-list
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DistroProfile {
+    Aosc,
+    AoscRetro,
+    /// Any other Debian-style system
+    Debian,
+}
+
+impl DistroProfile {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "aosc" => Some(DistroProfile::Aosc),
+            "aosc-retro" => Some(DistroProfile::AoscRetro),
+            "debian" => Some(DistroProfile::Debian),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DistroProfile::Aosc => "aosc",
+            DistroProfile::AoscRetro => "aosc-retro",
+            DistroProfile::Debian => "debian",
+        }
+    }
+
+    /// Pick the profile matching the `NAME` field of os-release
+    pub fn detect(os_release: &OsRelease) -> Self {
+        match os_release.name.as_str() {
+            "AOSC OS" => DistroProfile::Aosc,
+            "AOSC OS/Retro" => DistroProfile::AoscRetro,
+            _ => DistroProfile::Debian,
+        }
+    }
+
+    /// Status used when there is none yet, `None` if it must be created by hand
+    pub fn default_status(&self) -> Option<Status> {
+        match self {
+            DistroProfile::Aosc | DistroProfile::AoscRetro => Some(Status {
+                branch: "stable".to_string(),
+                component: vec!["main".to_string()],
+                mirror: indexmap! {"origin".to_string() => "https://repo.aosc.io".to_string()},
+                source_format: SourceFormat::default(),
+                signed_by: None,
+            }),
+            DistroProfile::Debian => None,
+        }
+    }
+
+    /// Path of the repository below the mirror URL
+    pub fn repo_directory(&self) -> &'static str {
+        match self {
+            DistroProfile::Aosc => "debs",
+            DistroProfile::AoscRetro => "debs-retro",
+            DistroProfile::Debian => "",
+        }
+    }
+
+    /// Endings of a custom mirror URL which mistakenly include the repository
+    /// directory
+    pub fn repo_directory_suffixes(&self) -> &'static [&'static str] {
+        match self {
+            DistroProfile::Aosc | DistroProfile::AoscRetro => {
+                &["debs", "debs/", "debs-retro", "debs-retro/"]
+            }
+            DistroProfile::Debian => &[],
+        }
+    }
+
+    /// File requested below a custom mirror URL to check that it can be
+    /// reached, `None` to add custom mirrors without checking
+    pub fn custom_mirror_probe(&self) -> Option<&'static str> {
+        match self {
+            DistroProfile::Aosc | DistroProfile::AoscRetro => Some("pool/stable/InRelease"),
+            DistroProfile::Debian => None,
+        }
+    }
+
+    /// Mirror which the others sync from, used as the reference when checking
+    /// how far mirrors are behind
+    pub fn origin_mirror(&self) -> Option<&'static str> {
+        match self {
+            DistroProfile::Aosc | DistroProfile::AoscRetro => Some("origin"),
+            DistroProfile::Debian => None,
+        }
+    }
+
+    /// Speed test probes used when neither the configuration nor the
+    /// repository data define any
+    pub fn speedtest_probes(&self) -> Vec<SpeedtestProbe> {
+        match self {
+            DistroProfile::Aosc | DistroProfile::AoscRetro => vec![SpeedtestProbe {
+                path: "misc/u-boot-sunxi-with-spl.bin".to_string(),
+                size: 398_445,
+                sha256: "98900564fb4d9c7d3b63f44686c5b8a120af94a51fc6ca595e1406d5d8cc0416"
+                    .to_string(),
+            }],
+            DistroProfile::Debian => Vec::new(),
+        }
+    }
+
+    /// Commands run in order to refresh the package lists once the sources
+    /// are written
+    pub fn refresh_commands(&self) -> &'static [&'static [&'static str]] {
+        match self {
+            DistroProfile::Aosc => &[&["atm", "refresh"], &["apt-get", "update"]],
+            DistroProfile::AoscRetro | DistroProfile::Debian => &[&["apt-get", "update"]],
+        }
+    }
+}
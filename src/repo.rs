@@ -0,0 +1,212 @@
+use indexmap::IndexMap;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, io::ErrorKind, path::Path};
+use url::Url;
+
+use crate::{fl, write_file, Context, Error, FileChange, Result};
+
+/// A branch defined in `branches.yml`
+#[derive(Deserialize, Serialize)]
+pub struct BranchInfo {
+    pub desc: String,
+    pub suites: Vec<String>,
+}
+
+/// A mirror defined in `mirrors.yml`
+#[derive(Deserialize, Serialize)]
+pub struct MirrorInfo {
+    pub desc: String,
+    pub url: String,
+}
+
+/// A file downloaded from mirrors to measure their speed, defined in
+/// `speedtest.yml`
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SpeedtestProbe {
+    /// Path below the mirror URL
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Hex-encoded SHA-256 checksum
+    pub sha256: String,
+}
+
+pub type BranchesData = HashMap<String, BranchInfo>;
+pub type MirrorsData = IndexMap<String, MirrorInfo>;
+pub type ComponentData = HashMap<String, String>;
+pub type CustomMirrorData = HashMap<String, String>;
+pub type SpeedtestData = Vec<SpeedtestProbe>;
+
+/// A mirror which can be enabled, either from the repository data or added
+/// by the user
+#[derive(Serialize)]
+pub struct AvailableMirror {
+    pub name: String,
+    /// Description from `mirrors.yml`, `None` for custom mirrors
+    pub desc: Option<String>,
+    pub url: String,
+    pub custom: bool,
+}
+
+/// Read one of the YAML files from the repository data or the custom mirror file
+pub fn read_distro_file<T: for<'de> Deserialize<'de>, P: AsRef<Path>>(file: P) -> Result<T> {
+    let path = file.as_ref();
+    let content = fs::read(path).map_err(|e| Error::ReadFile {
+        path: path.to_path_buf(),
+        source: e,
+    })?;
+
+    serde_yaml::from_slice(&content).map_err(|e| Error::ParseFile {
+        path: path.to_path_buf(),
+        source: Box::new(e),
+    })
+}
+
+/// Read a file which may not exist, `None` if it does not
+fn read_optional_file<T: for<'de> Deserialize<'de>, P: AsRef<Path>>(file: P) -> Result<Option<T>> {
+    match read_distro_file(file) {
+        Err(Error::ReadFile { source, .. }) if source.kind() == ErrorKind::NotFound => Ok(None),
+        result => result.map(Some),
+    }
+}
+
+/// Read the custom mirror file, which does not exist until a mirror is added
+fn read_custom_mirror_file(ctx: &Context) -> Result<CustomMirrorData> {
+    Ok(read_optional_file(ctx.custom_mirror_file())?.unwrap_or_default())
+}
+
+/// Files to download from mirrors when testing their speed, in order of
+/// preference
+///
+/// They are read from `/etc/apt-gen-list/speedtest.yml`, otherwise from
+/// `speedtest.yml` in the repository data, otherwise the distro profile
+/// decides.
+pub fn get_speedtest_probes(ctx: &Context) -> Result<SpeedtestData> {
+    let probes = match read_optional_file(ctx.speedtest_config_file())? {
+        Some(probes) => probes,
+        None => match read_optional_file(ctx.repo_speedtest_file())? {
+            Some(probes) => probes,
+            None => ctx.profile()?.speedtest_probes(),
+        },
+    };
+    if probes.is_empty() {
+        return Err(Error::NoSpeedtestProbe);
+    }
+
+    Ok(probes)
+}
+
+/// All mirrors from the repository data and the custom mirror file, sorted by name
+pub fn list_mirrors(ctx: &Context) -> Result<Vec<AvailableMirror>> {
+    let mut result_table = IndexMap::new();
+    let distro_mirror = read_distro_file::<MirrorsData, _>(ctx.repo_mirror_file())?;
+    for (mirror_name, mirror_info) in distro_mirror {
+        result_table.insert(
+            mirror_name.clone(),
+            AvailableMirror {
+                name: mirror_name,
+                desc: Some(mirror_info.desc),
+                url: mirror_info.url,
+                custom: false,
+            },
+        );
+    }
+    for (mirror_name, mirror_url) in read_custom_mirror_file(ctx)? {
+        result_table.insert(
+            mirror_name.clone(),
+            AvailableMirror {
+                name: mirror_name,
+                desc: None,
+                url: mirror_url,
+                custom: true,
+            },
+        );
+    }
+    result_table.sort_keys();
+
+    Ok(result_table.into_values().collect())
+}
+
+/// Add a mirror to the custom mirror file
+pub fn add_custom_mirror(ctx: &Context, mirror_name: &str, mirror_url: &str) -> Result<FileChange> {
+    if read_distro_file::<MirrorsData, _>(ctx.repo_mirror_file())?.contains_key(mirror_name) {
+        return Err(Error::CustomMirrorNameConflict(mirror_name.to_string()));
+    }
+    let url =
+        Url::parse(mirror_url).map_err(|_| Error::CustomMirrorNotUrl(mirror_url.to_string()))?;
+    let profile = ctx.profile()?;
+    for i in profile.repo_directory_suffixes() {
+        if mirror_url.ends_with(i) {
+            return Err(Error::DebsPathInUrl);
+        }
+    }
+    if let Some(probe) = profile.custom_mirror_probe() {
+        reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()?
+            .get(url.join(probe)?)
+            .send()
+            .map_err(Error::DownloadMirrorMetadataFailed)?;
+    }
+    let custom_mirror_file = ctx.custom_mirror_file();
+    let mut custom_mirror_data = read_custom_mirror_file(ctx)?;
+    if !custom_mirror_data.contains_key(mirror_name) {
+        custom_mirror_data.insert(mirror_name.to_string(), url.to_string());
+    } else {
+        warn!(
+            "{}",
+            fl!("custom-mirror-already-exist", mirror = mirror_name)
+        );
+    }
+
+    write_file(
+        ctx,
+        &custom_mirror_file,
+        &serde_yaml::to_string(&custom_mirror_data)?,
+    )
+}
+
+/// Remove a mirror from the custom mirror file
+pub fn remove_custom_mirror(ctx: &Context, mirror_name: &str) -> Result<FileChange> {
+    let custom_mirror_file = ctx.custom_mirror_file();
+    let mut custom_mirror = read_custom_mirror_file(ctx)?;
+    if !custom_mirror.contains_key(mirror_name) {
+        return Err(Error::CustomMirrorNotFound(mirror_name.to_string()));
+    } else {
+        custom_mirror.remove(mirror_name);
+    }
+
+    write_file(
+        ctx,
+        &custom_mirror_file,
+        &serde_yaml::to_string(&custom_mirror)?,
+    )
+}
+
+/// Look up the URL of a mirror from the repository data or the custom mirror file
+pub fn get_mirror_url(ctx: &Context, mirror_name: &str) -> Result<String> {
+    if let Some(mirror_info) =
+        read_distro_file::<MirrorsData, _>(ctx.repo_mirror_file())?.get(mirror_name)
+    {
+        return Ok(mirror_info.url.to_owned());
+    } else if let Some(mirror_url) = read_custom_mirror_file(ctx)?.get(mirror_name) {
+        return Ok(mirror_url.to_owned());
+    }
+
+    Err(Error::MirrorNotFound(mirror_name.to_string()))
+}
+
+/// Suites making up a branch
+pub fn get_branch_suites(ctx: &Context, branch_name: &str) -> Result<Vec<String>> {
+    Ok(read_distro_file::<BranchesData, _>(ctx.repo_branch_file())?
+        .get(branch_name)
+        .ok_or_else(|| Error::BranchDataError(branch_name.to_string()))?
+        .suites
+        .to_owned())
+}
+
+/// Path of the repository below the mirror URL
+pub fn get_directory_name(ctx: &Context) -> Result<&'static str> {
+    Ok(ctx.profile()?.repo_directory())
+}
@@ -0,0 +1,310 @@
+use serde::Serialize;
+use std::{fs, ops::Range, path::PathBuf};
+use url::Url;
+
+use crate::{
+    fl, get_branch_suites, get_directory_name, import::read_source_entries, Context, Error, Result,
+    SourceFormat, Status,
+};
+
+/// Lines delimiting the part of `/etc/apt/sources.list` owned by apt-gen-list
+const BEGIN_MARKER: &str = "# BEGIN apt-gen-list";
+const END_MARKER: &str = "# END apt-gen-list";
+/// First line of the files older versions of apt-gen-list wrote, in every language
+const LEGACY_HEADERS: &[&str] = &[
+    "# Generated by apt-gen-list. DO NOT EDIT THIS FILE!",
+    "# 本文件使用 apt-gen-list 生成，请勿编辑！",
+];
+
+/// A `deb` entry apt-gen-list does not manage which uses an enabled mirror
+#[derive(Serialize)]
+pub struct DuplicateEntry {
+    /// Path of the sources file on the target system
+    pub path: PathBuf,
+    pub line: usize,
+    pub mirror: String,
+}
+
+/// Render APT sources for `status` in its selected format
+///
+/// One-line sources are wrapped in the markers of the managed block.
+pub fn gen_sources_list_string(ctx: &Context, status: &Status) -> Result<String> {
+    match status.source_format {
+        SourceFormat::OneLine => gen_one_line_sources_string(ctx, status),
+        SourceFormat::Deb822 => gen_deb822_sources_string(ctx, status),
+    }
+}
+
+/// Content of the sources file of `format` once `status` is applied, `None`
+/// if the file should not exist
+///
+/// The deb822 sources file belongs to apt-gen-list, but in
+/// `/etc/apt/sources.list` only the lines between its begin and end markers
+/// do. Everything outside of them is kept byte-for-byte.
+pub fn gen_sources_file_string(
+    ctx: &Context,
+    status: &Status,
+    format: SourceFormat,
+) -> Result<Option<String>> {
+    let generated = if format == status.source_format {
+        Some(gen_sources_list_string(ctx, status)?)
+    } else {
+        None
+    };
+    if format == SourceFormat::Deb822 {
+        return Ok(generated);
+    }
+    let path = ctx.path(format.path());
+    let existing = fs::read_to_string(&path).ok();
+    // Without the end marker, replacing the block could remove the user's entries
+    if existing.as_deref().is_some_and(has_unterminated_block) {
+        return Err(Error::UnterminatedManagedBlock(path));
+    }
+
+    Ok(replace_managed_block(
+        existing.as_deref(),
+        generated.as_deref().unwrap_or_default(),
+    ))
+}
+
+/// Unmanaged `deb` entries which use a mirror enabled in `status`, so that
+/// APT sees them twice
+pub fn find_duplicate_entries(ctx: &Context, status: &Status) -> Result<Vec<DuplicateEntry>> {
+    let directory_name = get_directory_name(ctx)?;
+    let mut debs_urls = Vec::new();
+    for (mirror_name, mirror_url) in &status.mirror {
+        debs_urls.push((mirror_name, debs_url(mirror_url, directory_name)?));
+    }
+    let mut duplicates = Vec::new();
+    for entry in read_source_entries(ctx, true, &mut Vec::new())? {
+        if !entry.types.iter().any(|kind| kind == "deb") {
+            continue;
+        }
+        for uri in &entry.uris {
+            if let Some((mirror_name, _)) = debs_urls.iter().find(|(_, url)| is_same_url(url, uri))
+            {
+                duplicates.push(DuplicateEntry {
+                    path: entry.path.clone(),
+                    line: entry.line,
+                    mirror: mirror_name.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(duplicates)
+}
+
+/// URL of the repository of a mirror, as written to APT sources
+pub(crate) fn debs_url(mirror_url: &str, directory_name: &str) -> Result<Url> {
+    Ok(Url::parse(mirror_url)?.join(directory_name)?)
+}
+
+/// Whether `uri` from APT sources points at `url`
+pub(crate) fn is_same_url(url: &Url, uri: &str) -> bool {
+    Url::parse(uri)
+        .is_ok_and(|uri| uri.as_str().trim_end_matches('/') == url.as_str().trim_end_matches('/'))
+}
+
+/// Replace the lines of the managed block with empty lines, keeping the line
+/// numbers of everything else
+pub(crate) fn blank_managed_block(content: &str) -> String {
+    match owned_range(content) {
+        Some(range) => format!(
+            "{}{}{}",
+            &content[..range.start],
+            "\n".repeat(content[range.clone()].matches('\n').count()),
+            &content[range.end..]
+        ),
+        None => content.to_string(),
+    }
+}
+
+fn replace_managed_block(existing: Option<&str>, block: &str) -> Option<String> {
+    let result = match existing {
+        None => block.to_string(),
+        Some(existing) => match owned_range(existing) {
+            Some(range) => format!(
+                "{}{}{}",
+                &existing[..range.start],
+                block,
+                &existing[range.end..]
+            ),
+            None if existing.is_empty() || existing.ends_with('\n') => {
+                format!("{}{}", existing, block)
+            }
+            None => format!("{}\n{}", existing, block),
+        },
+    };
+
+    (!result.is_empty()).then_some(result)
+}
+
+/// Byte range of the content apt-gen-list owns
+fn owned_range(content: &str) -> Option<Range<usize>> {
+    // Older versions of apt-gen-list owned the whole file
+    managed_block_range(content).or_else(|| is_generated(content).then_some(0..content.len()))
+}
+
+/// Byte range of the managed block including both markers
+fn managed_block_range(content: &str) -> Option<Range<usize>> {
+    let mut start = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        match start {
+            None if line.trim_end() == BEGIN_MARKER => start = Some(offset),
+            Some(start) if line.trim_end() == END_MARKER => {
+                return Some(start..offset + line.len())
+            }
+            _ => (),
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+/// Whether the file has a begin marker but no managed block
+fn has_unterminated_block(content: &str) -> bool {
+    managed_block_range(content).is_none()
+        && content.lines().any(|line| line.trim_end() == BEGIN_MARKER)
+}
+
+/// Whether the whole file was written by an older version of apt-gen-list
+fn is_generated(content: &str) -> bool {
+    content
+        .lines()
+        .next()
+        .is_some_and(|line| LEGACY_HEADERS.contains(&line.trim_end()))
+}
+
+fn gen_one_line_sources_string(ctx: &Context, status: &Status) -> Result<String> {
+    let mut result = format!("{}\n{}\n", BEGIN_MARKER, fl!("generated-block"));
+    let directory_name = get_directory_name(ctx)?;
+    let options = match &status.signed_by {
+        Some(keyring) => format!("[signed-by={}] ", keyring),
+        None => String::new(),
+    };
+    for (_, mirror_url) in &status.mirror {
+        let debs_url = debs_url(mirror_url, directory_name)?;
+        for branch in get_branch_suites(ctx, &status.branch)? {
+            result.push_str(&format!(
+                "deb {}{} {} {}\n",
+                options,
+                debs_url.as_str(),
+                branch,
+                status.component.join(" ")
+            ));
+        }
+    }
+    result.push_str(END_MARKER);
+    result.push('\n');
+
+    Ok(result)
+}
+
+fn gen_deb822_sources_string(ctx: &Context, status: &Status) -> Result<String> {
+    let mut result = format!("{}\n", fl!("generated"));
+    let directory_name = get_directory_name(ctx)?;
+    let suites = get_branch_suites(ctx, &status.branch)?;
+    for (_, mirror_url) in &status.mirror {
+        let debs_url = debs_url(mirror_url, directory_name)?;
+        result.push_str(&format!(
+            "\nTypes: deb\nURIs: {}\nSuites: {}\nComponents: {}\n",
+            debs_url.as_str(),
+            suites.join(" "),
+            status.component.join(" ")
+        ));
+        if let Some(keyring) = &status.signed_by {
+            result.push_str(&format!("Signed-By: {}\n", keyring));
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: &str =
+        "# BEGIN apt-gen-list\ndeb https://repo.aosc.io/debs stable main\n# END apt-gen-list\n";
+
+    #[test]
+    fn managed_block_range_markers() {
+        let content = format!(
+            "deb https://example.com/debs stable main\n{}# mine\n",
+            BLOCK
+        );
+        let range = managed_block_range(&content).unwrap();
+
+        assert_eq!(&content[range], BLOCK);
+        assert_eq!(managed_block_range("# BEGIN apt-gen-list\ndeb a b\n"), None);
+        assert_eq!(managed_block_range("# END apt-gen-list\n"), None);
+    }
+
+    #[test]
+    fn replace_managed_block_keeps_user_entries() {
+        let block = "# BEGIN apt-gen-list\n# END apt-gen-list\n";
+        let existing = format!(
+            "# mine\n{}deb https://example.com/debs stable main\n",
+            BLOCK
+        );
+
+        assert_eq!(
+            replace_managed_block(Some(&existing), block).unwrap(),
+            format!(
+                "# mine\n{}deb https://example.com/debs stable main\n",
+                block
+            )
+        );
+    }
+
+    #[test]
+    fn replace_managed_block_without_markers() {
+        assert_eq!(replace_managed_block(None, BLOCK).unwrap(), BLOCK);
+        assert_eq!(replace_managed_block(Some(""), BLOCK).unwrap(), BLOCK);
+        assert_eq!(
+            replace_managed_block(Some("deb a b"), BLOCK).unwrap(),
+            format!("deb a b\n{}", BLOCK)
+        );
+        // Older versions of apt-gen-list owned the whole file
+        assert_eq!(
+            replace_managed_block(
+                Some("# Generated by apt-gen-list. DO NOT EDIT THIS FILE!\ndeb a b\n"),
+                BLOCK
+            )
+            .unwrap(),
+            BLOCK
+        );
+        assert_eq!(replace_managed_block(Some(BLOCK), ""), None);
+    }
+
+    #[test]
+    fn unterminated_block() {
+        let content = "# BEGIN apt-gen-list\ndeb https://repo.aosc.io/debs stable main\ndeb https://example.com/mine stable main\n";
+
+        assert!(has_unterminated_block(content));
+        assert!(!is_generated(content));
+        assert_eq!(owned_range(content), None);
+        assert!(!has_unterminated_block(BLOCK));
+        assert!(!has_unterminated_block("deb a b\n"));
+    }
+
+    #[test]
+    fn blank_managed_block_keeps_line_numbers() {
+        let content = format!("# mine\n{}deb a b\n", BLOCK);
+
+        assert_eq!(blank_managed_block(&content), "# mine\n\n\n\ndeb a b\n");
+        assert_eq!(
+            blank_managed_block("# 本文件使用 apt-gen-list 生成，请勿编辑！\ndeb a b\n"),
+            "\n\n"
+        );
+        // Only the exact header of older versions claims the whole file
+        assert_eq!(
+            blank_managed_block("# Mirrors for apt-gen-list users\ndeb a b\n"),
+            "# Mirrors for apt-gen-list users\ndeb a b\n"
+        );
+        assert_eq!(blank_managed_block("deb a b\n"), "deb a b\n");
+    }
+}
@@ -0,0 +1,997 @@
+use futures::{future, Future};
+use indexmap::IndexMap;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, ErrorKind},
+    iter,
+    time::{Duration, Instant},
+};
+use tokio::{
+    net::{self, TcpStream},
+    runtime::Builder,
+    sync::Semaphore,
+    time,
+};
+use url::Url;
+
+use crate::{
+    fl, get_speedtest_probes, read_distro_file, Context, Error, MirrorsData, Result, SpeedtestProbe,
+};
+
+/// Result of benchmarking a mirror
+#[derive(Clone, Deserialize, Serialize)]
+pub struct MirrorScore {
+    pub name: String,
+    /// `None` if the latency could not be measured
+    pub latency: Option<MirrorLatency>,
+    /// Path of the probe file which was downloaded, `None` if the test failed
+    pub probe: Option<String>,
+    /// Bytes received, `None` if the test failed
+    pub bytes: Option<u64>,
+    /// Median download speed, `None` if the test failed
+    pub bytes_per_second: Option<f64>,
+    /// Median seconds from requesting the probe file until its response
+    /// headers arrived, `None` if the test failed
+    pub ttfb: Option<f64>,
+    /// Median seconds taken to transfer the probe file once the response
+    /// started, `None` if the test failed
+    pub elapsed: Option<f64>,
+    /// Spread of the download speed over the successful rounds, `None` if
+    /// the test failed
+    pub stats: Option<SpeedStats>,
+    /// Number of rounds which failed
+    pub failed_rounds: usize,
+    /// `Ok` if any round succeeded, otherwise how the last one failed
+    #[serde(flatten)]
+    pub outcome: SpeedtestOutcome,
+}
+
+/// Round-trip times of a mirror, in seconds
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub struct MirrorLatency {
+    /// Time to open a TCP connection, after resolving the host name
+    pub connect: f64,
+    /// Time from sending a HEAD request for the first probe file until the
+    /// response headers arrive
+    pub ttfb: f64,
+}
+
+/// Download speeds of a mirror over several rounds, in bytes per second
+#[derive(Clone, Copy, Deserialize, Serialize)]
+pub struct SpeedStats {
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    /// Sample standard deviation, 0 for a single round
+    pub stddev: f64,
+    /// Number of successful rounds
+    pub rounds: usize,
+}
+
+impl SpeedStats {
+    fn new(samples: &[f64]) -> Option<Self> {
+        let rounds = samples.len();
+        let mean = samples.iter().sum::<f64>() / rounds as f64;
+        let variance = if rounds > 1 {
+            samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (rounds - 1) as f64
+        } else {
+            0.0
+        };
+
+        Some(SpeedStats {
+            median: median(samples)?,
+            min: samples.iter().copied().reduce(f64::min)?,
+            max: samples.iter().copied().reduce(f64::max)?,
+            stddev: variance.sqrt(),
+            rounds,
+        })
+    }
+}
+
+/// Progress of [`get_mirror_score_table`]
+pub enum SpeedtestProgress<'a> {
+    /// The latency of every mirror was measured, the download speed of these
+    /// mirrors is tested next
+    Start(&'a [&'a str]),
+    /// A mirror received `bytes` of a probe file of `size` bytes in `round`,
+    /// starting from 1
+    Download {
+        mirror: &'a str,
+        round: usize,
+        bytes: u64,
+        size: u64,
+    },
+    /// A mirror finished all of its rounds or ran out of time
+    Done(&'a str),
+}
+
+/// How [`get_mirror_score_table`] tests mirrors
+pub struct SpeedtestOptions {
+    /// How many mirrors may be tested at the same time, `None` for all of
+    /// them. Concurrent tests are faster but less accurate.
+    pub concurrency: Option<usize>,
+    /// Times each mirror is tested, rounds of a mirror never overlap
+    pub rounds: usize,
+    /// Only test the download speed of this many mirrors with the lowest
+    /// latency, `None` to test all of them
+    pub top: Option<usize>,
+    /// Time allowed to resolve a mirror and connect to it
+    pub connect_timeout: Duration,
+    /// Time allowed between receiving response headers or chunks of data
+    pub read_timeout: Duration,
+    /// Time after which the test stops and returns what was measured so
+    /// far, `None` to wait for every mirror
+    pub deadline: Option<Duration>,
+    /// Mirrors to test by name with their URL, `None` for every mirror in the
+    /// repository data
+    pub mirrors: Option<IndexMap<String, String>>,
+}
+
+impl Default for SpeedtestOptions {
+    fn default() -> Self {
+        SpeedtestOptions {
+            concurrency: Some(1),
+            rounds: 1,
+            top: None,
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(10),
+            deadline: None,
+            mirrors: None,
+        }
+    }
+}
+
+/// How sure [`fastest_mirror`] is that no other mirror is faster
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Confidence {
+    /// The slowest round of the fastest mirror beats every round of the runner-up
+    High,
+    /// The medians are further apart than their standard deviations
+    Medium,
+    Low,
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Confidence::High => fl!("confidence-high"),
+            Confidence::Medium => fl!("confidence-medium"),
+            Confidence::Low => fl!("confidence-low"),
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+/// How the test of a mirror ended
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "outcome", rename_all = "kebab-case")]
+pub enum SpeedtestOutcome {
+    Ok,
+    Timeout,
+    /// The mirror answered with an error status
+    HttpStatus {
+        status: u16,
+    },
+    Tls {
+        error: String,
+    },
+    /// The probe file does not match its size or checksum
+    ChecksumMismatch,
+    /// The download speed was not tested, other mirrors have a lower latency
+    Skipped,
+    /// The overall deadline passed before the mirror was tested
+    DeadlineExceeded,
+    /// Any other failure, such as the mirror being unreachable
+    Error {
+        error: String,
+    },
+}
+
+impl SpeedtestOutcome {
+    fn from_error(e: &Error) -> Self {
+        match e {
+            Error::MirrorDownloadFailed(_) => SpeedtestOutcome::ChecksumMismatch,
+            Error::Io(e) if e.kind() == ErrorKind::TimedOut => SpeedtestOutcome::Timeout,
+            Error::Network(e) if e.is_timeout() => SpeedtestOutcome::Timeout,
+            Error::Network(e) if e.status().is_some() => SpeedtestOutcome::HttpStatus {
+                status: e.status().map(|status| status.as_u16()).unwrap_or_default(),
+            },
+            Error::Network(e) => match tls_error(e) {
+                Some(tls_error) => SpeedtestOutcome::Tls {
+                    error: tls_error.to_string(),
+                },
+                None => SpeedtestOutcome::Error {
+                    error: e.to_string(),
+                },
+            },
+            e => SpeedtestOutcome::Error {
+                error: e.to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for SpeedtestOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            SpeedtestOutcome::Ok => fl!("speedtest-ok"),
+            SpeedtestOutcome::Timeout => fl!("speedtest-timeout"),
+            SpeedtestOutcome::HttpStatus { status } => {
+                let status = *status;
+                fl!("speedtest-http-status", status = status)
+            }
+            SpeedtestOutcome::Tls { error } => fl!("speedtest-tls", error = error.as_str()),
+            SpeedtestOutcome::ChecksumMismatch => fl!("speedtest-checksum-mismatch"),
+            SpeedtestOutcome::Skipped => fl!("speedtest-skipped"),
+            SpeedtestOutcome::DeadlineExceeded => fl!("speedtest-deadline-exceeded"),
+            SpeedtestOutcome::Error { error } => error.to_string(),
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+/// The TLS failure somewhere in the causes of `e`
+fn tls_error(e: &reqwest::Error) -> Option<&native_tls::Error> {
+    let mut source = std::error::Error::source(e);
+    while let Some(e) = source {
+        if let Some(tls_error) = e.downcast_ref::<native_tls::Error>() {
+            return Some(tls_error);
+        }
+        source = e.source();
+    }
+
+    None
+}
+
+/// A successful download of a probe file
+struct Measurement {
+    probe: String,
+    bytes: u64,
+    ttfb: f64,
+    elapsed: f64,
+}
+
+impl MirrorScore {
+    fn new(name: &str, latency: Option<MirrorLatency>, results: Vec<Result<Measurement>>) -> Self {
+        let mut measurements = Vec::new();
+        let mut last_error = None;
+        let mut failed_rounds = 0;
+        for result in results {
+            match result {
+                Ok(measurement) => measurements.push(measurement),
+                Err(e) => {
+                    failed_rounds += 1;
+                    last_error = Some(e);
+                }
+            }
+        }
+        let speeds = measurements
+            .iter()
+            .map(|measurement| measurement.bytes as f64 / measurement.elapsed)
+            .collect::<Vec<_>>();
+        let elapsed = measurements
+            .iter()
+            .map(|measurement| measurement.elapsed)
+            .collect::<Vec<_>>();
+        let ttfb = measurements
+            .iter()
+            .map(|measurement| measurement.ttfb)
+            .collect::<Vec<_>>();
+        let stats = SpeedStats::new(&speeds);
+        let outcome = match (&stats, last_error) {
+            (Some(_), _) => SpeedtestOutcome::Ok,
+            (None, Some(e)) => SpeedtestOutcome::from_error(&e),
+            (None, None) => SpeedtestOutcome::DeadlineExceeded,
+        };
+        let first = measurements.into_iter().next();
+
+        MirrorScore {
+            name: name.to_string(),
+            latency,
+            probe: first.as_ref().map(|measurement| measurement.probe.clone()),
+            bytes: first.map(|measurement| measurement.bytes),
+            bytes_per_second: stats.map(|stats| stats.median),
+            ttfb: median(&ttfb),
+            elapsed: median(&elapsed),
+            failed_rounds,
+            stats,
+            outcome,
+        }
+    }
+
+    /// A mirror whose download speed was not tested, because it is skipped
+    /// or its latency could not be measured
+    fn untested(name: &str, latency: Result<MirrorLatency>) -> Self {
+        let outcome = match &latency {
+            Ok(_) => SpeedtestOutcome::Skipped,
+            Err(Error::DeadlineExceeded) => SpeedtestOutcome::DeadlineExceeded,
+            Err(e) => SpeedtestOutcome::from_error(e),
+        };
+
+        MirrorScore {
+            name: name.to_string(),
+            latency: latency.ok(),
+            probe: None,
+            bytes: None,
+            bytes_per_second: None,
+            ttfb: None,
+            elapsed: None,
+            stats: None,
+            failed_rounds: 0,
+            outcome,
+        }
+    }
+
+    /// Position in the table, mirrors which passed by speed, then skipped
+    /// mirrors by latency, then failed mirrors
+    fn rank(&self) -> (u8, f64) {
+        match (self.bytes_per_second, &self.outcome) {
+            (Some(speed), _) => (0, -speed),
+            (None, SpeedtestOutcome::Skipped) => (
+                1,
+                self.latency.map_or(f64::INFINITY, |latency| latency.ttfb),
+            ),
+            _ => (2, 0.0),
+        }
+    }
+}
+
+fn median(samples: &[f64]) -> Option<f64> {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let middle = sorted.len() / 2;
+    match sorted.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((sorted[middle - 1] + sorted[middle]) / 2.0),
+        _ => Some(sorted[middle]),
+    }
+}
+
+/// Mirrors a speed test with `options` measures
+pub(crate) fn speedtest_mirrors(
+    ctx: &Context,
+    options: &SpeedtestOptions,
+) -> Result<IndexMap<String, String>> {
+    Ok(match &options.mirrors {
+        Some(mirrors) => mirrors.clone(),
+        None => read_distro_file::<MirrorsData, _>(ctx.repo_mirror_file())?
+            .into_iter()
+            .map(|(mirror_name, mirror_info)| (mirror_name, mirror_info.url))
+            .collect(),
+    })
+}
+
+/// Benchmark mirrors, fastest first followed by the mirrors which failed
+///
+/// `options.mirrors` selects what is tested, e.g. the enabled mirrors, the
+/// custom mirrors as well or mirrors named by the user. By default every
+/// mirror in the repository data is tested.
+///
+/// The latency of all mirrors is measured first, as many at a time as
+/// `options.concurrency` allows. With
+/// `options.top`, only that many mirrors with the lowest latency have their
+/// download speed tested, the others are [`SpeedtestOutcome::Skipped`].
+///
+/// Each mirror is tested with the first probe file from
+/// [`get_speedtest_probes`] it has, the speed is computed from the bytes
+/// actually received.
+///
+/// Use [`fastest_mirror`] to pick a mirror from the result.
+///
+/// Mirrors are ranked by their median speed over `options.rounds` rounds.
+/// Once `options.deadline` passes, mirrors are ranked by the rounds finished
+/// so far and those without any are [`SpeedtestOutcome::DeadlineExceeded`].
+/// `on_progress` is called as downloads start, advance and finish.
+pub fn get_mirror_score_table<F: FnMut(SpeedtestProgress)>(
+    ctx: &Context,
+    options: &SpeedtestOptions,
+    on_progress: F,
+) -> Result<Vec<MirrorScore>> {
+    let mirrors_indexmap = speedtest_mirrors(ctx, options)?;
+    let probes = get_speedtest_probes(ctx)?;
+    let rounds = options.rounds.max(1);
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    let client = Client::builder()
+        .connect_timeout(options.connect_timeout)
+        .build()?;
+    let start = time::Instant::now();
+    let deadline = options
+        .deadline
+        .and_then(|deadline| start.checked_add(deadline));
+    // A stalled mirror must not use up the time left for downloads
+    let latency_deadline = options
+        .deadline
+        .and_then(|deadline| start.checked_add(deadline / 2));
+    // Shared by both phases, concurrent probes would skew the latency as well
+    let semaphore = Semaphore::new(options.concurrency.unwrap_or(mirrors_indexmap.len()).max(1));
+    let latencies = runtime.block_on(future::join_all(mirrors_indexmap.values().map(|x| {
+        before_deadline(latency_deadline, async {
+            let _permit = semaphore.acquire().await;
+            get_mirror_latency(x, &probes[0], &client, options).await
+        })
+    })));
+    let mut mirrors_score_table = Vec::new();
+    let mut candidates = Vec::new();
+    match options.top {
+        Some(top) => {
+            let mut measured = Vec::new();
+            for (mirror, latency) in mirrors_indexmap.iter().zip(latencies) {
+                match latency {
+                    Ok(latency) => measured.push((mirror, latency)),
+                    Err(e) => mirrors_score_table.push(MirrorScore::untested(mirror.0, Err(e))),
+                }
+            }
+            measured.sort_by(|a, b| a.1.ttfb.total_cmp(&b.1.ttfb));
+            for (index, (mirror, latency)) in measured.into_iter().enumerate() {
+                if index < top {
+                    candidates.push((mirror, Some(latency)));
+                } else {
+                    mirrors_score_table.push(MirrorScore::untested(mirror.0, Ok(latency)));
+                }
+            }
+        }
+        None => {
+            candidates = mirrors_indexmap
+                .iter()
+                .zip(latencies)
+                .map(|(mirror, latency)| (mirror, latency.ok()))
+                .collect();
+        }
+    }
+    // Every future runs on this thread, so they can share the callback
+    let on_progress = RefCell::new(on_progress);
+    let report = |progress| on_progress.borrow_mut()(progress);
+    let names = candidates
+        .iter()
+        .map(|((mirror_name, _), _)| mirror_name.as_str())
+        .collect::<Vec<_>>();
+    report(SpeedtestProgress::Start(&names));
+    let results = runtime.block_on(future::join_all(candidates.iter().map(
+        |((mirror_name, mirror_url), _)| async {
+            let mut results = Vec::new();
+            let permit = before_deadline(deadline, async { Ok(semaphore.acquire().await) }).await;
+            let rounds = if permit.is_ok() { rounds } else { 0 };
+            for round in 1..=rounds {
+                let download = get_mirror_speed_score(
+                    mirror_name,
+                    mirror_url,
+                    &probes,
+                    &client,
+                    options,
+                    |bytes, size| {
+                        report(SpeedtestProgress::Download {
+                            mirror: mirror_name,
+                            round,
+                            bytes,
+                            size,
+                        })
+                    },
+                );
+                match before_deadline(deadline, download).await {
+                    Err(Error::DeadlineExceeded) => break,
+                    result => results.push(result),
+                }
+            }
+            drop(permit);
+            report(SpeedtestProgress::Done(mirror_name));
+
+            results
+        },
+    )));
+    for (((mirror_name, _), latency), results) in candidates.into_iter().zip(results) {
+        mirrors_score_table.push(MirrorScore::new(mirror_name, latency, results));
+    }
+    sort_score_table(&mut mirrors_score_table);
+
+    Ok(mirrors_score_table)
+}
+
+/// Order mirrors as in the table returned by [`get_mirror_score_table`]
+pub(crate) fn sort_score_table(mirrors_score_table: &mut [MirrorScore]) {
+    mirrors_score_table.sort_by(|a, b| {
+        let (a_class, a_value) = a.rank();
+        let (b_class, b_value) = b.rank();
+        a_class.cmp(&b_class).then(a_value.total_cmp(&b_value))
+    });
+}
+
+/// The fastest mirror in a table returned by [`get_mirror_score_table`]
+///
+/// Fails with [`Error::DeadlineExceeded`] if no mirror passed because the
+/// deadline cut the test short.
+pub fn fastest_mirror(mirrors_score_table: &[MirrorScore]) -> Result<&MirrorScore> {
+    match mirrors_score_table.first() {
+        Some(score) if score.bytes_per_second.is_some() => Ok(score),
+        _ if mirrors_score_table
+            .iter()
+            .any(|score| matches!(score.outcome, SpeedtestOutcome::DeadlineExceeded)) =>
+        {
+            Err(Error::DeadlineExceeded)
+        }
+        _ => Err(Error::MirrorTestFailed),
+    }
+}
+
+/// Up to `count` of the fastest mirrors in a table returned by
+/// [`get_mirror_score_table`], fastest first, and at least the fastest one
+pub fn fastest_mirrors(
+    mirrors_score_table: &[MirrorScore],
+    count: usize,
+) -> Result<&[MirrorScore]> {
+    fastest_mirror(mirrors_score_table)?;
+    let passed = mirrors_score_table
+        .iter()
+        .take_while(|score| score.bytes_per_second.is_some())
+        .count();
+
+    Ok(&mirrors_score_table[..passed.min(count.max(1))])
+}
+
+/// By how many percent the fastest mirror of a table returned by
+/// [`get_mirror_score_table`] beats `mirror_name`, `None` if either has no speed
+pub fn fastest_mirror_lead(mirrors_score_table: &[MirrorScore], mirror_name: &str) -> Option<f64> {
+    let fastest = mirrors_score_table.first()?.bytes_per_second?;
+    let speed = mirrors_score_table
+        .iter()
+        .find(|score| score.name == mirror_name)?
+        .bytes_per_second?;
+
+    Some(fastest / speed * 100.0 - 100.0)
+}
+
+/// How clearly the fastest mirror of a table returned by
+/// [`get_mirror_score_table`] beats the runner-up, `None` if fewer than two
+/// mirrors passed the test
+pub fn fastest_mirror_confidence(mirrors_score_table: &[MirrorScore]) -> Option<Confidence> {
+    let fastest = mirrors_score_table.first()?.stats?;
+    let runner_up = mirrors_score_table.get(1)?.stats?;
+    if fastest.rounds < 2 || runner_up.rounds < 2 {
+        return Some(Confidence::Low);
+    }
+    if fastest.min > runner_up.max {
+        Some(Confidence::High)
+    } else if fastest.median - fastest.stddev > runner_up.median + runner_up.stddev {
+        Some(Confidence::Medium)
+    } else {
+        Some(Confidence::Low)
+    }
+}
+
+/// Which mirrors [`select_mirrors`] enables
+pub enum MirrorSelection<'a> {
+    /// The fastest mirrors, fastest first
+    Fastest(&'a [MirrorScore]),
+    /// The current mirror is at most `lead` percent slower than the fastest
+    /// one, so it keeps the first slot and the fastest others follow
+    KeepCurrent { mirrors: Vec<&'a str>, lead: f64 },
+    /// The current mirror is the only one to enable and already the fastest
+    Unchanged,
+}
+
+/// Pick `count` mirrors to enable from a table returned by
+/// [`get_mirror_score_table`]
+///
+/// Without a `margin` these are the fastest ones. With a `margin` in percent,
+/// the `current` mirror stays first unless the fastest one beats it by more.
+pub fn select_mirrors<'a>(
+    mirrors_score_table: &'a [MirrorScore],
+    count: usize,
+    current: &str,
+    margin: Option<f64>,
+) -> Result<MirrorSelection<'a>> {
+    let fastest = fastest_mirrors(mirrors_score_table, count)?;
+    let margin = match margin {
+        Some(margin) => margin,
+        None => return Ok(MirrorSelection::Fastest(fastest)),
+    };
+    if fastest[0].name == current {
+        return Ok(match fastest {
+            [_] => MirrorSelection::Unchanged,
+            _ => MirrorSelection::Fastest(fastest),
+        });
+    }
+    let current = match mirrors_score_table
+        .iter()
+        .find(|score| score.name == current)
+    {
+        Some(current) => current.name.as_str(),
+        None => return Ok(MirrorSelection::Fastest(fastest)),
+    };
+    match fastest_mirror_lead(mirrors_score_table, current) {
+        Some(lead) if lead <= margin => Ok(MirrorSelection::KeepCurrent {
+            mirrors: iter::once(current)
+                .chain(
+                    fastest
+                        .iter()
+                        .map(|score| score.name.as_str())
+                        .filter(|name| *name != current),
+                )
+                .take(fastest.len())
+                .collect(),
+            lead,
+        }),
+        _ => Ok(MirrorSelection::Fastest(fastest)),
+    }
+}
+
+/// Run `test`, failing with [`Error::DeadlineExceeded`] once `deadline` passes
+async fn before_deadline<T, F: Future<Output = Result<T>>>(
+    deadline: Option<time::Instant>,
+    test: F,
+) -> Result<T> {
+    match deadline {
+        Some(deadline) => time::timeout_at(deadline, test)
+            .await
+            .unwrap_or(Err(Error::DeadlineExceeded)),
+        None => test.await,
+    }
+}
+
+/// Parse a mirror URL so that probe paths are joined below it, custom mirrors
+/// may lack the trailing slash
+fn base_url(mirror_url: &str) -> Result<Url> {
+    let mut url = Url::parse(mirror_url)?;
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+
+    Ok(url)
+}
+
+/// Run `step` of a request, failing with a timeout after `timeout`
+async fn within<T, E: Into<Error>, F: Future<Output = std::result::Result<T, E>>>(
+    timeout: Duration,
+    step: F,
+) -> Result<T> {
+    match time::timeout(timeout, step).await {
+        Ok(result) => result.map_err(Into::into),
+        Err(_) => Err(io::Error::from(ErrorKind::TimedOut).into()),
+    }
+}
+
+/// Measure the latency of a mirror, the HEAD request does not need to succeed
+async fn get_mirror_latency(
+    mirror_url: &str,
+    probe: &SpeedtestProbe,
+    client: &Client,
+    options: &SpeedtestOptions,
+) -> Result<MirrorLatency> {
+    let mirror_url = base_url(mirror_url)?;
+    let host = mirror_url
+        .host_str()
+        .ok_or(Error::InvalidUrl(url::ParseError::EmptyHost))?;
+    let port = mirror_url.port_or_known_default().unwrap_or(80);
+    let timer = Instant::now();
+    let address = within(options.connect_timeout, net::lookup_host((host, port)))
+        .await?
+        .next()
+        .ok_or_else(|| io::Error::from(ErrorKind::AddrNotAvailable))?;
+    let timer_connect = Instant::now();
+    within(
+        options.connect_timeout.saturating_sub(timer.elapsed()),
+        TcpStream::connect(address),
+    )
+    .await?;
+    let connect = timer_connect.elapsed().as_secs_f64();
+    let timer = Instant::now();
+    within(
+        options.connect_timeout.saturating_add(options.read_timeout),
+        client.head(mirror_url.join(&probe.path)?).send(),
+    )
+    .await?;
+
+    Ok(MirrorLatency {
+        connect,
+        ttfb: timer.elapsed().as_secs_f64(),
+    })
+}
+
+/// Download a probe file from a mirror, `on_received` is called with the
+/// bytes received so far and the size of the probe file
+async fn get_mirror_speed_score<F: Fn(u64, u64)>(
+    mirror_name: &str,
+    mirror_url: &str,
+    probes: &[SpeedtestProbe],
+    client: &Client,
+    options: &SpeedtestOptions,
+    on_received: F,
+) -> Result<Measurement> {
+    let mirror_url = base_url(mirror_url)?;
+    let mut result = Err(Error::NoSpeedtestProbe);
+    for probe in probes {
+        let timer = Instant::now();
+        let response = within(
+            options.connect_timeout.saturating_add(options.read_timeout),
+            client.get(mirror_url.join(&probe.path)?).send(),
+        )
+        .await
+        .and_then(|response| Ok(response.error_for_status()?));
+        let mut response = match response {
+            Ok(response) => response,
+            Err(Error::Network(e)) if is_not_found(&e) => {
+                result = Err(e.into());
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let ttfb = timer.elapsed().as_secs_f64();
+        on_received(0, probe.size);
+        let timer = Instant::now();
+        let mut hasher = Sha256::new();
+        let mut bytes = 0;
+        while let Some(chunk) = within(options.read_timeout, response.chunk()).await? {
+            hasher.update(&chunk);
+            bytes += chunk.len() as u64;
+            on_received(bytes, probe.size);
+            // The checksum cannot match anymore, do not wait for the rest
+            if bytes > probe.size {
+                break;
+            }
+        }
+        let elapsed = timer.elapsed().as_secs_f64();
+        if bytes != probe.size
+            || !hex::encode(hasher.finalize()).eq_ignore_ascii_case(&probe.sha256)
+        {
+            return Err(Error::MirrorDownloadFailed(mirror_name.to_string()));
+        }
+
+        return Ok(Measurement {
+            probe: probe.path.clone(),
+            bytes,
+            ttfb,
+            elapsed,
+        });
+    }
+
+    result
+}
+
+/// A mirror lacking a probe file is tested with the next one
+fn is_not_found(e: &reqwest::Error) -> bool {
+    e.status() == Some(StatusCode::NOT_FOUND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A table of mirrors named `a`, `b`, ... ordered like [`get_mirror_score_table`]
+    /// returns it, `None` for one which failed
+    fn score_table(speeds: &[Option<f64>]) -> Vec<MirrorScore> {
+        speeds
+            .iter()
+            .zip('a'..)
+            .map(|(speed, name)| MirrorScore {
+                name: name.to_string(),
+                latency: None,
+                probe: None,
+                bytes: None,
+                bytes_per_second: *speed,
+                ttfb: None,
+                elapsed: None,
+                stats: speed.and_then(|speed| SpeedStats::new(&[speed])),
+                failed_rounds: 0,
+                outcome: match speed {
+                    Some(_) => SpeedtestOutcome::Ok,
+                    None => SpeedtestOutcome::Timeout,
+                },
+            })
+            .collect()
+    }
+
+    /// A round which downloaded `speed` bytes in one second
+    fn round(speed: f64) -> Result<Measurement> {
+        Ok(Measurement {
+            probe: "misc/probe.bin".to_string(),
+            bytes: speed as u64,
+            ttfb: 0.1,
+            elapsed: 1.0,
+        })
+    }
+
+    #[test]
+    fn speed_stats_of_rounds() {
+        let stats = SpeedStats::new(&[300.0, 100.0, 200.0, 400.0]).unwrap();
+
+        assert_eq!(stats.median, 250.0);
+        assert_eq!(stats.min, 100.0);
+        assert_eq!(stats.max, 400.0);
+        assert!((stats.stddev - 129.099_444_873_580_56).abs() < 1e-9);
+        assert_eq!(stats.rounds, 4);
+        assert_eq!(SpeedStats::new(&[100.0]).unwrap().stddev, 0.0);
+        assert!(SpeedStats::new(&[]).is_none());
+    }
+
+    #[test]
+    fn score_of_failed_rounds() {
+        let score = MirrorScore::new(
+            "a",
+            None,
+            vec![round(100.0), Err(Error::MirrorTestFailed), round(300.0)],
+        );
+        assert_eq!(score.bytes_per_second, Some(200.0));
+        assert_eq!(score.failed_rounds, 1);
+        assert!(matches!(score.outcome, SpeedtestOutcome::Ok));
+
+        let score = MirrorScore::new("b", None, vec![Err(Error::MirrorTestFailed)]);
+        assert_eq!(score.bytes_per_second, None);
+        assert!(!matches!(score.outcome, SpeedtestOutcome::Ok));
+    }
+
+    #[test]
+    fn rank_by_median_speed() {
+        // `a` has the higher mean, `b` the higher median
+        let mut table = vec![
+            MirrorScore::untested(
+                "skipped",
+                Ok(MirrorLatency {
+                    connect: 0.1,
+                    ttfb: 0.1,
+                }),
+            ),
+            MirrorScore::new("failed", None, vec![Err(Error::MirrorTestFailed)]),
+            MirrorScore::new("a", None, vec![round(100.0), round(100.0), round(1000.0)]),
+            MirrorScore::new("b", None, vec![round(200.0), round(200.0), round(200.0)]),
+        ];
+        sort_score_table(&mut table);
+        let names = table
+            .iter()
+            .map(|score| score.name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["b", "a", "skipped", "failed"]);
+        assert_eq!(fastest_mirror(&table).unwrap().name, "b");
+        assert_eq!(fastest_mirror_lead(&table, "a"), Some(100.0));
+        assert_eq!(fastest_mirror_lead(&table, "failed"), None);
+    }
+
+    #[test]
+    fn confidence_of_fastest_mirror() {
+        let confidence = |fastest: &[f64], runner_up: &[f64]| {
+            let table = [fastest, runner_up]
+                .iter()
+                .zip(["a", "b"])
+                .map(|(speeds, name)| {
+                    MirrorScore::new(
+                        name,
+                        None,
+                        speeds.iter().map(|speed| round(*speed)).collect(),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            fastest_mirror_confidence(&table)
+        };
+
+        assert!(confidence(&[300.0, 310.0], &[200.0, 210.0]) == Some(Confidence::High));
+        assert!(
+            confidence(&[300.0, 310.0, 190.0], &[200.0, 205.0, 210.0]) == Some(Confidence::Medium)
+        );
+        assert!(confidence(&[300.0, 100.0], &[200.0, 190.0]) == Some(Confidence::Low));
+        // A single round cannot tell the mirrors apart
+        assert!(confidence(&[300.0], &[100.0]) == Some(Confidence::Low));
+        assert!(confidence(&[300.0], &[]).is_none());
+    }
+
+    #[test]
+    fn partial_results_past_deadline() {
+        let mut table = vec![
+            MirrorScore::untested("late", Err(Error::DeadlineExceeded)),
+            MirrorScore::new("unfinished", None, vec![]),
+            MirrorScore::new("a", None, vec![round(100.0)]),
+        ];
+        sort_score_table(&mut table);
+
+        assert_eq!(fastest_mirror(&table).unwrap().name, "a");
+        for score in &table[1..] {
+            assert!(matches!(score.outcome, SpeedtestOutcome::DeadlineExceeded));
+        }
+        assert!(matches!(
+            fastest_mirror(&table[1..]),
+            Err(Error::DeadlineExceeded)
+        ));
+    }
+
+    #[test]
+    fn before_deadline_cuts_off_tests() {
+        let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+        let passed = runtime.block_on(async {
+            let deadline = time::Instant::now() + Duration::from_millis(10);
+            before_deadline(Some(deadline), async {
+                time::sleep(Duration::from_secs(10)).await;
+                Ok(())
+            })
+            .await
+        });
+        let finished = runtime.block_on(before_deadline(None, async { Ok(1) }));
+
+        assert!(matches!(passed, Err(Error::DeadlineExceeded)));
+        assert_eq!(finished.unwrap(), 1);
+    }
+
+    fn names(selection: MirrorSelection) -> Vec<String> {
+        match selection {
+            MirrorSelection::Fastest(fastest) => {
+                fastest.iter().map(|score| score.name.clone()).collect()
+            }
+            MirrorSelection::KeepCurrent { mirrors, .. } => {
+                mirrors.into_iter().map(str::to_string).collect()
+            }
+            MirrorSelection::Unchanged => vec![],
+        }
+    }
+
+    #[test]
+    fn select_fastest_mirrors() {
+        let table = score_table(&[Some(300.0), Some(200.0), Some(100.0), None]);
+        assert_eq!(names(select_mirrors(&table, 1, "c", None).unwrap()), ["a"]);
+        assert_eq!(
+            names(select_mirrors(&table, 2, "c", None).unwrap()),
+            ["a", "b"]
+        );
+        // Only mirrors which passed the test are enabled
+        assert_eq!(
+            names(select_mirrors(&table, 5, "c", None).unwrap()),
+            ["a", "b", "c"]
+        );
+        assert_eq!(names(select_mirrors(&table, 0, "c", None).unwrap()), ["a"]);
+        assert!(matches!(
+            select_mirrors(&score_table(&[None]), 1, "a", None),
+            Err(Error::MirrorTestFailed)
+        ));
+    }
+
+    #[test]
+    fn select_mirrors_within_margin() {
+        let table = score_table(&[Some(300.0), Some(200.0), Some(100.0)]);
+        // `a` is 50% faster than `b`
+        match select_mirrors(&table, 1, "b", Some(50.0)).unwrap() {
+            MirrorSelection::KeepCurrent { mirrors, lead } => {
+                assert_eq!(mirrors, ["b"]);
+                assert!((lead - 50.0).abs() < 1e-9);
+            }
+            _ => panic!("expected the current mirror to be kept"),
+        }
+        assert_eq!(
+            names(select_mirrors(&table, 2, "b", Some(60.0)).unwrap()),
+            ["b", "a"]
+        );
+        assert_eq!(
+            names(select_mirrors(&table, 2, "c", Some(200.0)).unwrap()),
+            ["c", "a"]
+        );
+        assert!(matches!(
+            select_mirrors(&table, 1, "a", Some(10.0)).unwrap(),
+            MirrorSelection::Unchanged
+        ));
+        assert_eq!(
+            names(select_mirrors(&table, 2, "a", Some(10.0)).unwrap()),
+            ["a", "b"]
+        );
+    }
+
+    #[test]
+    fn select_mirrors_beyond_margin() {
+        let table = score_table(&[Some(300.0), Some(200.0), None]);
+        assert_eq!(
+            names(select_mirrors(&table, 1, "b", Some(49.0)).unwrap()),
+            ["a"]
+        );
+        // A current mirror which failed or is unknown is never kept
+        assert_eq!(
+            names(select_mirrors(&table, 1, "c", Some(1000.0)).unwrap()),
+            ["a"]
+        );
+        assert_eq!(
+            names(select_mirrors(&table, 2, "z", Some(1000.0)).unwrap()),
+            ["a", "b"]
+        );
+    }
+}
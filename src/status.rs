@@ -0,0 +1,546 @@
+use indexmap::{indexmap, IndexMap};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    mem,
+    path::Path,
+};
+use url::Url;
+
+use crate::{
+    fl, get_mirror_url, import_sources, is_root, read_distro_file, sources::is_same_url,
+    transaction::atomic_write, BranchesData, ComponentData, Context, Error, MirrorDrift,
+    MirrorDriftReason, Result,
+};
+
+const APT_SOURCE_FILE: &str = "/etc/apt/sources.list";
+const APT_DEB822_SOURCE_FILE: &str = "/etc/apt/sources.list.d/apt-gen-list.sources";
+
+/// The configuration apt-gen-list generates APT sources from
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Status {
+    pub branch: String,
+    pub component: Vec<String>,
+    /// Enabled mirrors and their URLs, in order of preference
+    pub mirror: IndexMap<String, String>,
+    #[serde(default)]
+    pub source_format: SourceFormat,
+    /// Keyring referenced by the generated sources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed_by: Option<String>,
+}
+
+/// Format of the generated APT sources
+#[derive(Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SourceFormat {
+    /// One-line `deb URL suite comps` entries in `/etc/apt/sources.list`
+    #[default]
+    OneLine,
+    /// deb822 stanzas in `/etc/apt/sources.list.d/apt-gen-list.sources`
+    Deb822,
+}
+
+impl SourceFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "one-line" => Some(SourceFormat::OneLine),
+            "deb822" => Some(SourceFormat::Deb822),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SourceFormat::OneLine => "one-line",
+            SourceFormat::Deb822 => "deb822",
+        }
+    }
+
+    /// Where sources of this format are written on the target system
+    pub fn path(&self) -> &'static str {
+        match self {
+            SourceFormat::OneLine => APT_SOURCE_FILE,
+            SourceFormat::Deb822 => APT_DEB822_SOURCE_FILE,
+        }
+    }
+}
+
+/// Schema version of the status file written by this version of apt-gen-list
+pub const STATUS_VERSION: u32 = 2;
+
+/// Upgrades of the status file, the one at index N turns version N into N + 1
+const MIGRATIONS: [fn(&Context, Value) -> Result<Value>; STATUS_VERSION as usize] =
+    [migrate_v0, migrate_v1];
+
+/// The status as stored in the status file
+#[derive(Serialize)]
+struct StatusFile<'a> {
+    version: u32,
+    #[serde(flatten)]
+    status: &'a Status,
+}
+
+impl Status {
+    pub fn set_branch(&mut self, ctx: &Context, new_branch: &str) -> Result<()> {
+        if read_distro_file::<BranchesData, _>(ctx.repo_branch_file())?.contains_key(new_branch) {
+            self.branch = new_branch.to_string();
+        } else {
+            return Err(Error::BranchNotFound(new_branch.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Replace all enabled mirrors with `new_mirror`
+    pub fn set_mirror(&mut self, ctx: &Context, new_mirror: &str) -> Result<()> {
+        self.mirror = indexmap! {new_mirror.to_string() => get_mirror_url(ctx, new_mirror)?};
+
+        Ok(())
+    }
+
+    /// Replace the enabled mirrors with `new_mirrors`, keeping their order
+    pub fn set_mirrors(&mut self, ctx: &Context, new_mirrors: &[&str]) -> Result<()> {
+        let mut mirror = IndexMap::new();
+        for new_mirror in new_mirrors {
+            mirror.insert(new_mirror.to_string(), get_mirror_url(ctx, new_mirror)?);
+        }
+        if mirror.is_empty() {
+            return Err(Error::NoDeleteOnlyMirror);
+        }
+        self.mirror = mirror;
+
+        Ok(())
+    }
+
+    pub fn add_mirrors(&mut self, ctx: &Context, entry: &[&str]) -> Result<()> {
+        for i in entry {
+            let mirror_url = get_mirror_url(ctx, i)?;
+            if self.mirror.contains_key(*i) {
+                warn!("{}", fl!("mirror-already-enabled", mirror = i.to_string()));
+            } else {
+                self.mirror.insert(i.to_string(), mirror_url);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_mirrors(&mut self, entry: &[&str]) -> Result<()> {
+        if self.mirror.len() == 1 {
+            return Err(Error::NoDeleteOnlyMirror);
+        }
+        for i in entry {
+            if self.mirror.contains_key(*i) {
+                self.mirror.remove(*i);
+            } else {
+                return Err(Error::MirrorNotFound(i.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Update the URL of each enabled mirror from the repository data and the
+    /// custom mirror file
+    ///
+    /// Mirrors which are no longer defined keep their stored URL, or are
+    /// removed with `drop_missing` unless that would leave none. Returns the
+    /// mirrors which were out of date.
+    pub fn refresh_mirror_urls(
+        &mut self,
+        ctx: &Context,
+        drop_missing: bool,
+    ) -> Result<Vec<MirrorDrift>> {
+        let mut drifts = Vec::new();
+        for (mirror_name, mirror_url) in self.mirror.iter_mut() {
+            let reason = match get_mirror_url(ctx, mirror_name) {
+                // The same URL may be written with or without a trailing slash
+                Ok(url) if Url::parse(&url).is_ok_and(|url| is_same_url(&url, mirror_url)) => {
+                    continue
+                }
+                Ok(url) => MirrorDriftReason::UrlChanged { expected_url: url },
+                Err(Error::MirrorNotFound(_)) => MirrorDriftReason::Removed,
+                Err(e) => return Err(e),
+            };
+            let url = match &reason {
+                MirrorDriftReason::UrlChanged { expected_url } => {
+                    mem::replace(mirror_url, expected_url.clone())
+                }
+                MirrorDriftReason::Removed => mirror_url.clone(),
+            };
+            drifts.push(MirrorDrift {
+                name: mirror_name.clone(),
+                url,
+                reason,
+            });
+        }
+        let removed = drifts
+            .iter()
+            .filter(|drift| matches!(drift.reason, MirrorDriftReason::Removed))
+            .map(|drift| drift.name.as_str())
+            .collect::<Vec<_>>();
+        if drop_missing && !removed.is_empty() {
+            if removed.len() == self.mirror.len() {
+                return Err(Error::NoDeleteOnlyMirror);
+            }
+            self.mirror
+                .retain(|mirror_name, _| !removed.contains(&mirror_name.as_str()));
+        }
+
+        Ok(drifts)
+    }
+
+    pub fn add_components(&mut self, ctx: &Context, entries: &[&str]) -> Result<()> {
+        for entry in entries {
+            let entry_str = entry.to_string();
+            if self.component.contains(&entry_str) {
+                warn!("{}", fl!("comp-already-enabled", comp = entry_str.clone()));
+            } else if read_distro_file::<ComponentData, _>(ctx.repo_component_file())?
+                .contains_key(&entry_str)
+            {
+                self.component.push(entry_str);
+            } else {
+                return Err(Error::ComponentNotFound(entry_str));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_components(&mut self, entry: &[&str]) -> Result<()> {
+        if !entry.contains(&"main") {
+            for i in entry {
+                if let Some(index) = self.component.iter().position(|v| v == i) {
+                    self.component.remove(index);
+                } else {
+                    warn!("{}", fl!("comp-not-enabled", comp = i.to_string()));
+                }
+            }
+        } else {
+            return Err(Error::NoDeleteOnlyComponent);
+        }
+
+        Ok(())
+    }
+}
+
+/// Read the status file of the target system
+///
+/// Status files of older versions are migrated to [`STATUS_VERSION`] in
+/// memory, [`apply_status`](crate::apply_status) rewrites them and keeps the
+/// original next to them. If there is no
+/// status yet, it is imported from the APT sources in use, entries which
+/// cannot be expressed as a status are left as they are. Only when no entry
+/// can be imported is it created from the default of the distro profile.
+pub fn read_status(ctx: &Context) -> Result<Status> {
+    let status_file = ctx.status_file();
+    if !status_file.is_file() && !is_root() {
+        return Err(Error::StatusFileNotFound(status_file));
+    }
+    let status = match fs::read(&status_file) {
+        Ok(file) => return migrate_status(ctx, &status_file, &file),
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            return Err(Error::ReadFile {
+                path: status_file,
+                source: e,
+            })
+        }
+        Err(_) => match import_sources(ctx) {
+            Ok(result) => {
+                for entry in &result.unmapped {
+                    warn!(
+                        "{}",
+                        fl!(
+                            "import-unmapped",
+                            path = entry.path.display().to_string(),
+                            line = entry.line,
+                            reason = entry.reason.to_string()
+                        )
+                    );
+                }
+                result.status
+            }
+            Err(e) => match ctx.profile()?.default_status() {
+                Some(status) => {
+                    warn!("{}", fl!("import-default", error = e.to_string()));
+                    status
+                }
+                None => {
+                    warn!("{}", e);
+                    return Err(Error::StatusFileNotFound(status_file));
+                }
+            },
+        },
+    };
+    if !ctx.dry_run() {
+        atomic_write(&status_file, status_file_string(&status)?.as_bytes())?;
+    }
+
+    Ok(status)
+}
+
+/// Render `status` as the content of the status file
+pub fn status_file_string(status: &Status) -> Result<String> {
+    let status_file = StatusFile {
+        version: STATUS_VERSION,
+        status,
+    };
+
+    Ok(format!("{}\n", serde_json::to_string(&status_file)?))
+}
+
+/// Parse the status file at `path`, migrating it from older versions
+fn migrate_status(ctx: &Context, path: &Path, file: &[u8]) -> Result<Status> {
+    let value = serde_json::from_slice::<Value>(file)
+        .ok()
+        .filter(Value::is_object)
+        .ok_or_else(|| Error::StatusFileCorrupt(path.to_path_buf()))?;
+    let version =
+        status_version(&value).ok_or_else(|| Error::StatusFileCorrupt(path.to_path_buf()))?;
+    if version > STATUS_VERSION {
+        return Err(Error::StatusFileTooNew {
+            path: path.to_path_buf(),
+            version,
+        });
+    }
+    if version == STATUS_VERSION {
+        return serde_json::from_value(value)
+            .map_err(|_| Error::StatusFileCorrupt(path.to_path_buf()));
+    }
+    // Written in the current version by the next apply only
+    MIGRATIONS[version as usize..]
+        .iter()
+        .try_fold(value, |value, migration| migration(ctx, value))
+        .and_then(|value| Ok(serde_json::from_value::<Status>(value)?))
+        .map_err(|e| Error::StatusMigrationFailed {
+            path: path.to_path_buf(),
+            version,
+            source: Box::new(e),
+        })
+}
+
+/// Copy the status file at `path` to e.g. `status.json.v1.bak` before it is
+/// replaced, if it has an older version
+///
+/// An existing copy is never overwritten, it holds the original file of that
+/// version.
+pub(crate) fn backup_status_file(path: &Path) -> Result<()> {
+    let file = match fs::read(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let version = serde_json::from_slice::<Value>(&file)
+        .ok()
+        .filter(Value::is_object)
+        .and_then(|value| status_version(&value));
+    let version = match version {
+        Some(version) if version < STATUS_VERSION => version,
+        _ => return Ok(()),
+    };
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{}.bak", version));
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&backup)
+    {
+        Ok(mut backup) => {
+            backup.write_all(&file)?;
+            backup.sync_all()?;
+        }
+        Err(e) if e.kind() != ErrorKind::AlreadyExists => return Err(e.into()),
+        Err(_) => (),
+    }
+
+    Ok(())
+}
+
+/// Schema version of a status file, files without one predate versioning
+fn status_version(value: &Value) -> Option<u32> {
+    match value.get("version") {
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok()),
+        None if value.get("mirror").is_some_and(Value::is_array) => Some(0),
+        None => Some(1),
+    }
+}
+
+/// Version 0 only listed mirror names, version 1 records their URLs
+fn migrate_v0(ctx: &Context, mut value: Value) -> Result<Value> {
+    let mut mirror = Map::new();
+    if let Some(names) = value["mirror"].as_array() {
+        for name in names.iter().filter_map(Value::as_str) {
+            mirror.insert(name.to_string(), get_mirror_url(ctx, name)?.into());
+        }
+    }
+    value["mirror"] = Value::Object(mirror);
+
+    Ok(value)
+}
+
+/// Version 2 adds the `version` field itself
+fn migrate_v1(_ctx: &Context, value: Value) -> Result<Value> {
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::env;
+
+    /// A target root with only the mirrors of the repository data
+    fn test_context(name: &str) -> Context {
+        let root = env::temp_dir().join(format!("apt-gen-list-{}-{}", name, std::process::id()));
+        let data = root.join("usr/share/distro-repository-data");
+        fs::create_dir_all(&data).unwrap();
+        fs::write(
+            data.join("mirrors.yml"),
+            "origin:\n  desc: AOSC main repository\n  url: https://repo.aosc.io/\n",
+        )
+        .unwrap();
+
+        Context::new(root, true, false, None)
+    }
+
+    #[test]
+    fn status_version_detection() {
+        assert_eq!(
+            status_version(&json!({"version": 2, "mirror": {}})),
+            Some(2)
+        );
+        assert_eq!(status_version(&json!({"mirror": ["origin"]})), Some(0));
+        assert_eq!(
+            status_version(&json!({"mirror": {"origin": "https://repo.aosc.io/"}})),
+            Some(1)
+        );
+        assert_eq!(status_version(&json!({"version": "2"})), None);
+        assert_eq!(status_version(&json!({"version": u64::MAX})), None);
+    }
+
+    #[test]
+    fn migrate_status_from_v0() {
+        let ctx = test_context("migrate-v0");
+        let status = migrate_status(
+            &ctx,
+            &ctx.status_file(),
+            br#"{"branch":"stable","component":["main"],"mirror":["origin"]}"#,
+        )
+        .unwrap();
+        fs::remove_dir_all(ctx.path("/")).unwrap();
+
+        assert_eq!(status.branch, "stable");
+        assert_eq!(status.component, ["main"]);
+        assert_eq!(
+            status.mirror.into_iter().collect::<Vec<_>>(),
+            [("origin".to_string(), "https://repo.aosc.io/".to_string())]
+        );
+        assert!(status.source_format == SourceFormat::OneLine);
+    }
+
+    #[test]
+    fn backup_status_file_once() {
+        let ctx = test_context("status-backup");
+        let path = ctx.status_file();
+        let backup = ctx.path("/var/lib/apt/gen/status.json.v1.bak");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let v1 = r#"{"branch":"stable","component":["main"],"mirror":{}}"#;
+        fs::write(&path, v1).unwrap();
+        backup_status_file(&path).unwrap();
+        let first_backup = fs::read_to_string(&backup);
+        // A second v1 file does not replace the original one
+        fs::write(&path, v1.replace("stable", "testing")).unwrap();
+        backup_status_file(&path).unwrap();
+        let second_backup = fs::read_to_string(&backup);
+        fs::write(
+            &path,
+            status_file_string(&migrate_status(&ctx, &path, v1.as_bytes()).unwrap()).unwrap(),
+        )
+        .unwrap();
+        fs::remove_file(&backup).unwrap();
+        backup_status_file(&path).unwrap();
+        let current_backed_up = backup.exists();
+        fs::remove_dir_all(ctx.path("/")).unwrap();
+
+        assert_eq!(first_backup.unwrap(), v1);
+        assert_eq!(second_backup.unwrap(), v1);
+        assert!(!current_backed_up);
+    }
+
+    #[test]
+    fn migrate_status_keeps_mirror_order() {
+        let ctx = test_context("mirror-order");
+        let current = br#"{"version":2,"branch":"stable","component":["main"],"mirror":{"tuna":"https://mirrors.tuna.tsinghua.edu.cn/anthon/","origin":"https://repo.aosc.io/"},"source_format":"one-line"}"#;
+        let v1 = br#"{"branch":"stable","component":["main"],"mirror":{"tuna":"https://mirrors.tuna.tsinghua.edu.cn/anthon/","origin":"https://repo.aosc.io/"}}"#;
+        let from_current = migrate_status(&ctx, &ctx.status_file(), current).unwrap();
+        let from_v1 = migrate_status(&ctx, &ctx.status_file(), v1).unwrap();
+        fs::remove_dir_all(ctx.path("/")).unwrap();
+
+        for status in [&from_current, &from_v1] {
+            assert_eq!(status.mirror.keys().collect::<Vec<_>>(), ["tuna", "origin"]);
+        }
+        assert_eq!(
+            status_file_string(&from_current).unwrap(),
+            format!("{}\n", String::from_utf8_lossy(current))
+        );
+    }
+
+    #[test]
+    fn refresh_mirror_urls_drift() {
+        let ctx = test_context("refresh-mirror-urls");
+        let mut status = Status {
+            branch: "stable".to_string(),
+            component: vec!["main".to_string()],
+            mirror: indexmap! {
+                "origin".to_string() => "https://repo.aosc.io".to_string(),
+            },
+            source_format: SourceFormat::OneLine,
+            signed_by: None,
+        };
+        let unchanged = status.refresh_mirror_urls(&ctx, true).unwrap();
+        status.mirror = indexmap! {
+            "origin".to_string() => "https://old.aosc.io/".to_string(),
+            "gone".to_string() => "https://gone.example.com/".to_string(),
+        };
+        let drifts = status.refresh_mirror_urls(&ctx, true).unwrap();
+        fs::remove_dir_all(ctx.path("/")).unwrap();
+
+        assert!(unchanged.is_empty());
+        assert_eq!(drifts.len(), 2);
+        assert!(matches!(
+            &drifts[0].reason,
+            MirrorDriftReason::UrlChanged { expected_url } if expected_url == "https://repo.aosc.io/"
+        ));
+        assert!(matches!(drifts[1].reason, MirrorDriftReason::Removed));
+        assert_eq!(
+            status.mirror.into_iter().collect::<Vec<_>>(),
+            [("origin".to_string(), "https://repo.aosc.io/".to_string())]
+        );
+    }
+
+    #[test]
+    fn migrate_status_current_and_newer() {
+        let ctx = test_context("migrate-current");
+        let current = migrate_status(
+            &ctx,
+            &ctx.status_file(),
+            br#"{"version":2,"branch":"stable","component":["main"],"mirror":{"origin":"https://repo.aosc.io/"},"source_format":"deb822"}"#,
+        );
+        let newer = migrate_status(&ctx, &ctx.status_file(), br#"{"version":3}"#);
+        let corrupt = migrate_status(&ctx, &ctx.status_file(), b"[]");
+        fs::remove_dir_all(ctx.path("/")).unwrap();
+
+        assert!(current.unwrap().source_format == SourceFormat::Deb822);
+        assert!(matches!(
+            newer,
+            Err(Error::StatusFileTooNew { version: 3, .. })
+        ));
+        assert!(matches!(corrupt, Err(Error::StatusFileCorrupt(_))));
+    }
+}
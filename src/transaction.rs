@@ -0,0 +1,276 @@
+use nix::{
+    errno::Errno,
+    fcntl::{flock, FlockArg},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsString,
+    fs, io,
+    io::{ErrorKind, Write},
+    os::unix::{fs::PermissionsExt, io::AsRawFd},
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, Result};
+
+/// A file touched by a transaction and what it contained before
+#[derive(Deserialize, Serialize)]
+struct Backup {
+    path: PathBuf,
+    /// `None` if the file did not exist
+    content: Option<Vec<u8>>,
+}
+
+/// A group of file changes which either all take effect or are all rolled back
+///
+/// The previous content of every file is saved to a journal before anything
+/// is changed, so that an interrupted transaction can be rolled back on the
+/// next run with [`Transaction::recover`].
+pub struct Transaction {
+    journal: PathBuf,
+    backups: Vec<Backup>,
+}
+
+impl Transaction {
+    /// Start a transaction which may change `paths`
+    pub fn begin(journal: PathBuf, paths: &[&Path]) -> Result<Self> {
+        let mut backups = Vec::new();
+        for path in paths {
+            let content = match fs::read(path) {
+                Ok(content) => Some(content),
+                Err(e) if e.kind() == ErrorKind::NotFound => None,
+                Err(e) => return Err(e.into()),
+            };
+            backups.push(Backup {
+                path: path.to_path_buf(),
+                content,
+            });
+        }
+        atomic_write(&journal, &serde_json::to_vec(&backups)?)?;
+
+        Ok(Transaction { journal, backups })
+    }
+
+    /// Atomically replace the content of `path`, which must be one of the
+    /// paths passed to [`Transaction::begin`]
+    pub fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+        atomic_write(path, content)
+    }
+
+    /// Remove `path` if it exists, which must be one of the paths passed to
+    /// [`Transaction::begin`]
+    pub fn remove(&self, path: &Path) -> Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Keep all changes made so far
+    pub fn commit(self) -> Result<()> {
+        fs::remove_file(&self.journal)?;
+
+        Ok(())
+    }
+
+    /// Restore every file to what it was before the transaction began
+    pub fn rollback(self) -> Result<()> {
+        restore(&self.backups)?;
+        fs::remove_file(&self.journal)?;
+
+        Ok(())
+    }
+
+    /// Roll back a transaction left behind by an interrupted run, returns
+    /// whether there was one
+    pub fn recover(journal: &Path) -> Result<bool> {
+        let backups: Vec<Backup> = match fs::read(journal) {
+            Ok(file) => serde_json::from_slice(&file)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        restore(&backups)?;
+        fs::remove_file(journal)?;
+
+        Ok(true)
+    }
+}
+
+/// An exclusive lock on a file, released when dropped
+///
+/// Held from before the status is read until the files are committed, so that
+/// a second run cannot roll back or overwrite files while the first is still
+/// changing them, nor apply changes computed from what the first replaced.
+pub struct Lock {
+    _file: fs::File,
+}
+
+impl Lock {
+    /// Wait until no other run holds the lock
+    pub fn acquire(path: &Path) -> Result<Self> {
+        let file = open_lock_file(path)?;
+        flock(file.as_raw_fd(), FlockArg::LockExclusive).map_err(io::Error::from)?;
+
+        Ok(Lock { _file: file })
+    }
+
+    /// Take the lock if it is free, `None` if another run holds it
+    pub fn try_acquire(path: &Path) -> Result<Option<Self>> {
+        let file = open_lock_file(path)?;
+        match flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock) {
+            Ok(()) => Ok(Some(Lock { _file: file })),
+            Err(Errno::EWOULDBLOCK) => Ok(None),
+            Err(e) => Err(io::Error::from(e).into()),
+        }
+    }
+}
+
+fn open_lock_file(path: &Path) -> Result<fs::File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    Ok(fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?)
+}
+
+fn restore(backups: &[Backup]) -> Result<()> {
+    for backup in backups {
+        match &backup.content {
+            Some(content) => atomic_write(&backup.path, content)?,
+            None => match fs::remove_file(&backup.path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+                _ => (),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Write `content` to a temporary file next to `path`, flush it to disk and
+/// rename it over `path`, so that `path` never holds partial content
+///
+/// The file keeps its permissions, a new one is readable by everyone like
+/// other APT configuration regardless of the umask.
+pub fn atomic_write(path: &Path, content: &[u8]) -> Result<()> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| Error::InvalidPath(path.to_path_buf()))?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::InvalidPath(path.to_path_buf()))?;
+    fs::create_dir_all(parent)?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(".tmp");
+    let temp_path = parent.join(temp_name);
+    let permissions = fs::metadata(path)
+        .map(|metadata| metadata.permissions())
+        .unwrap_or_else(|_| fs::Permissions::from_mode(0o644));
+    let result = (|| -> io::Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        file.set_permissions(permissions)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if let Err(e) = result {
+        fs::remove_file(&temp_path).ok();
+        return Err(e.into());
+    }
+    fs::File::open(parent)?.sync_all()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("apt-gen-list-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    /// A transaction over an existing file `a` and a missing file `b`, both changed
+    fn changed_transaction(dir: &Path) -> Transaction {
+        let (a, b) = (dir.join("a"), dir.join("b"));
+        fs::write(&a, "old").unwrap();
+        let transaction = Transaction::begin(dir.join("journal"), &[&a, &b]).unwrap();
+        transaction.write(&a, b"new").unwrap();
+        transaction.write(&b, b"created").unwrap();
+
+        transaction
+    }
+
+    #[test]
+    fn transaction_commit() {
+        let dir = test_dir("transaction-commit");
+        changed_transaction(&dir).commit().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dir.join("b")).unwrap(), "created");
+        assert!(!dir.join("journal").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn transaction_rollback() {
+        let dir = test_dir("transaction-rollback");
+        changed_transaction(&dir).rollback().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "old");
+        assert!(!dir.join("b").exists());
+        assert!(!dir.join("journal").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn transaction_recover() {
+        let dir = test_dir("transaction-recover");
+        let journal = dir.join("journal");
+        assert!(!Transaction::recover(&journal).unwrap());
+        // Dropped without commit or rollback, like a run which crashed
+        let transaction = changed_transaction(&dir);
+        transaction.remove(&dir.join("a")).unwrap();
+        drop(transaction);
+        assert!(journal.exists());
+        assert!(Transaction::recover(&journal).unwrap());
+        assert_eq!(fs::read_to_string(dir.join("a")).unwrap(), "old");
+        assert!(!dir.join("b").exists());
+        assert!(!Transaction::recover(&journal).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn lock_is_exclusive() {
+        let dir = test_dir("lock");
+        let path = dir.join("lock");
+        let lock = Lock::acquire(&path).unwrap();
+        assert!(Lock::try_acquire(&path).unwrap().is_none());
+        drop(lock);
+        assert!(Lock::try_acquire(&path).unwrap().is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn atomic_write_keeps_permissions() {
+        let dir = test_dir("atomic-write");
+        let path = dir.join("file");
+        atomic_write(&path, b"first").unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o644);
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        atomic_write(&path, b"second").unwrap();
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert!(!dir.join(".file.tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}